@@ -0,0 +1,4 @@
+// Rebuilds when a migration changes so that the embedded migrations stay in sync
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
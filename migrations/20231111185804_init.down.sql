@@ -1,5 +1,6 @@
 -- Add down migration script here
-DROP TABLE IF EXISTS "users";
+-- Codes reference users, which reference languages
 DROP TABLE IF EXISTS "codes";
-DROP TABLE IF EXISTS "languages";
-DROP TABLE IF EXISTS "tokens";
\ No newline at end of file
+DROP TABLE IF EXISTS "tokens";
+DROP TABLE IF EXISTS "users";
+DROP TABLE IF EXISTS "languages";
\ No newline at end of file
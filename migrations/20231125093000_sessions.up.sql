@@ -0,0 +1,26 @@
+-- Add up migration script here
+CREATE TABLE
+    "sessions" (
+        id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+        user_id UUID NOT NULL,
+        user_agent TEXT,
+        ip_address VARCHAR(255),
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+        last_used_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+        CONSTRAINT fk_user
+            FOREIGN KEY(user_id)
+                REFERENCES users(id)
+    );
+
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
+
+-- Tokens issued before sessions existed cannot be attached to one
+DELETE FROM tokens;
+
+ALTER TABLE tokens
+    ADD COLUMN session_id UUID NOT NULL,
+    ADD CONSTRAINT fk_session
+        FOREIGN KEY(session_id)
+            REFERENCES sessions(id);
+
+CREATE INDEX tokens_session_id_idx ON tokens (session_id);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS "security_events";
+ALTER TABLE tokens DROP COLUMN IF EXISTS parent_id;
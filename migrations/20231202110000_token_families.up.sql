@@ -0,0 +1,20 @@
+-- Add up migration script here
+ALTER TABLE tokens ADD COLUMN parent_id UUID;
+
+CREATE INDEX tokens_parent_id_idx ON tokens (user_id, parent_id);
+
+CREATE TABLE
+    "security_events" (
+        id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+        user_id UUID NOT NULL,
+        session_id UUID,
+        kind VARCHAR(50) NOT NULL,
+        user_agent TEXT,
+        ip_address VARCHAR(255),
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+        CONSTRAINT fk_user
+            FOREIGN KEY(user_id)
+                REFERENCES users(id)
+    );
+
+CREATE INDEX security_events_user_id_idx ON security_events (user_id);
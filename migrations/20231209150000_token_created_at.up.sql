@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE tokens ADD COLUMN created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DELETE FROM codes;
+
+ALTER TABLE codes ALTER COLUMN code TYPE VARCHAR(8);
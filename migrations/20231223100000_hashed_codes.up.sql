@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- Codes are now stored as an HMAC-SHA256 hex digest, pending plain text codes are dropped
+DELETE FROM codes;
+
+ALTER TABLE codes ALTER COLUMN code TYPE VARCHAR(64);
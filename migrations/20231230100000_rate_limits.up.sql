@@ -0,0 +1,8 @@
+-- Add up migration script here
+CREATE TABLE
+    "rate_limits" (
+        key VARCHAR(320) NOT NULL PRIMARY KEY,
+        tokens DOUBLE PRECISION NOT NULL,
+        last_allowed BOOLEAN NOT NULL DEFAULT TRUE,
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL
+    );
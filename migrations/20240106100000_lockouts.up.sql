@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE
+    "lockouts" (
+        user_id UUID NOT NULL PRIMARY KEY,
+        exhausted_codes SMALLINT NOT NULL DEFAULT 0,
+        window_started_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+        lock_count SMALLINT NOT NULL DEFAULT 0,
+        locked_until TIMESTAMP WITH TIME ZONE,
+        CONSTRAINT fk_user
+            FOREIGN KEY(user_id)
+                REFERENCES users(id)
+    );
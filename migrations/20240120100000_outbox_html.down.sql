@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE outbox DROP COLUMN IF EXISTS html_body;
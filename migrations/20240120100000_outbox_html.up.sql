@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE outbox ADD COLUMN html_body TEXT;
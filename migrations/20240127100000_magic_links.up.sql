@@ -0,0 +1,12 @@
+-- Add up migration script here
+-- Login links carry an opaque random token, only its SHA-256 digest is stored
+CREATE TABLE
+    "magic_links" (
+        token_hash VARCHAR(64) NOT NULL PRIMARY KEY,
+        user_id UUID NOT NULL REFERENCES users (id),
+        redirect_path TEXT,
+        expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL
+    );
+
+CREATE INDEX magic_links_user_id_idx ON magic_links (user_id);
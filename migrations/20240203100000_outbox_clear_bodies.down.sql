@@ -0,0 +1,3 @@
+-- Add down migration script here
+-- The cleared bodies cannot be restored
+SELECT 1;
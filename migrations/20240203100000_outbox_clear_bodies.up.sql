@@ -0,0 +1,2 @@
+-- Add up migration script here
+UPDATE outbox SET body = '', html_body = NULL WHERE status <> 'pending';
@@ -0,0 +1,5 @@
+pub mod users_query_schema;
+pub mod update_role_request_schema;
+
+pub use users_query_schema::UsersQuerySchema;
+pub use update_role_request_schema::UpdateRoleRequestSchema;
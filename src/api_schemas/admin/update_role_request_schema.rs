@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateRoleRequestSchema {
+    #[schema(example = "admin")]
+    pub role: String,
+}
@@ -0,0 +1,17 @@
+use serde::Deserialize;
+use utoipa::IntoParams;
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UsersQuerySchema {
+    // Starts at 1, bounded so that the offset cannot overflow
+    #[validate(range(min = 1, max = 1_000_000, code = "invalid_page"))]
+    pub page: Option<i64>,
+    // 20 by default, at most 100
+    pub limit: Option<i64>,
+    // Part of the email, case insensitive
+    pub email: Option<String>,
+    pub role: Option<String>,
+    pub verified: Option<bool>,
+}
@@ -1,7 +1,17 @@
 use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Debug, Deserialize)]
+use crate::api_schemas::validation::{normalize_email, validate_code};
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct ConfirmCodeRequestSchema {
+    #[serde(deserialize_with = "normalize_email")]
+    #[validate(email(code = "invalid_email"), length(max = 254, code = "invalid_email"))]
     pub email: String,
+    #[validate(length(min = 4, max = 32, code = "invalid_code"), custom = "validate_code")]
     pub code: String,
+    // Return the tokens in the response body instead of cookies (mobile and CLI clients)
+    #[serde(rename = "tokensInBody", default)]
+    pub tokens_in_body: bool,
 }
@@ -1,6 +1,25 @@
 use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Debug, Deserialize)]
+use crate::api_schemas::validation::normalize_email;
+
+#[derive(Debug, Deserialize, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginMode {
+    #[default]
+    Code,
+    Link,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct LoginRequestSchema {
+    #[serde(deserialize_with = "normalize_email")]
+    #[validate(email(code = "invalid_email"), length(max = 254, code = "invalid_email"))]
     pub email: String,
+    #[serde(default)]
+    pub mode: LoginMode,
+    // Path on the front application the magic link redirects to
+    #[serde(rename = "redirectPath")]
+    pub redirect_path: Option<String>,
 }
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MagicLinkQuerySchema {
+    pub token: String,
+}
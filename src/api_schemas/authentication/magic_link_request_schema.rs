@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+// Form posted by the confirmation page of a login link
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MagicLinkRequestSchema {
+    pub token: String,
+}
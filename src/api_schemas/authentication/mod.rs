@@ -1,7 +1,15 @@
 pub mod register_request_schema;
 pub mod login_request_schema;
 pub mod confirm_code_request_schema;
+pub mod refresh_request_schema;
+pub mod magic_link_query_schema;
+pub mod magic_link_request_schema;
+pub mod unlock_query_schema;
 
 pub use register_request_schema::RegisterRequestSchema;
-pub use login_request_schema::LoginRequestSchema;
-pub use confirm_code_request_schema::ConfirmCodeRequestSchema;
\ No newline at end of file
+pub use login_request_schema::{LoginRequestSchema, LoginMode};
+pub use confirm_code_request_schema::ConfirmCodeRequestSchema;
+pub use refresh_request_schema::RefreshRequestSchema;
+pub use magic_link_query_schema::MagicLinkQuerySchema;
+pub use magic_link_request_schema::MagicLinkRequestSchema;
+pub use unlock_query_schema::UnlockQuerySchema;
\ No newline at end of file
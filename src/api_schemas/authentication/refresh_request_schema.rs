@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RefreshRequestSchema {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+}
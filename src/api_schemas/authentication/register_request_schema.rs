@@ -1,7 +1,15 @@
 use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Debug, Deserialize)]
+use crate::api_schemas::validation::{normalize_email, validate_language};
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct RegisterRequestSchema {
+    #[serde(deserialize_with = "normalize_email")]
+    #[validate(email(code = "invalid_email"), length(max = 254, code = "invalid_email"))]
     pub email: String,
-    pub language: String,
+    // Negotiated from the Accept-Language header when missing
+    #[validate(custom = "validate_language")]
+    pub language: Option<String>,
 }
@@ -1,3 +1,7 @@
 mod authentication;
+mod admin;
+pub mod validation;
 
-pub use authentication::*;
\ No newline at end of file
+pub use authentication::*;
+pub use admin::*;
+pub use validation::ValidatedJson;
\ No newline at end of file
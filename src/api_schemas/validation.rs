@@ -0,0 +1,84 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    ops::Deref,
+    pin::Pin };
+use actix_web::{dev::Payload, error::{JsonPayloadError, PathError, QueryPayloadError}, web, Error, FromRequest, HttpRequest};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use validator::{Validate, ValidationError};
+
+use crate::shared::api_error::ApiError;
+
+// Emails are stored and compared trimmed and lowercase
+pub fn normalize_email<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let email = String::deserialize(deserializer)?;
+    Ok(email.trim().to_lowercase())
+}
+
+// Codes are generated from the numeric or alphanumeric alphabet
+pub fn validate_code(code: &str) -> Result<(), ValidationError> {
+    if !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ValidationError::new("invalid_code"));
+    }
+    Ok(())
+}
+
+// Language codes are ISO 639-1, the supported ones are checked against the languages table
+pub fn validate_language(language: &str) -> Result<(), ValidationError> {
+    if language.len() != 2 || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(ValidationError::new("invalid_language"));
+    }
+    Ok(())
+}
+
+// JSON body validated before the handler runs, failures are answered with the invalid fields
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidatedJson<T> {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+
+        Box::pin(async move {
+            let body = json.await?.into_inner();
+            body.validate().map_err(ApiError::from)?;
+            Ok(ValidatedJson(body))
+        })
+    }
+}
+
+// Registered with `JsonConfig` so that malformed bodies get the API error format
+pub fn json_error_handler(err: JsonPayloadError, _: &HttpRequest) -> Error {
+    let detail = match &err {
+        JsonPayloadError::Deserialize(err) => err.to_string(),
+        JsonPayloadError::ContentType => "expected an application/json content type".to_string(),
+        _ => err.to_string(),
+    };
+    ApiError::InvalidJson { detail }.into()
+}
+
+// Registered with `QueryConfig` and `PathConfig`, the serde errors do not name the field so the
+// whole query string or path is reported
+pub fn query_error_handler(err: QueryPayloadError, _: &HttpRequest) -> Error {
+    log::debug!("Invalid query string: {}", err);
+    invalid_part("query", "invalid_query")
+}
+
+pub fn path_error_handler(err: PathError, _: &HttpRequest) -> Error {
+    log::debug!("Invalid path: {}", err);
+    invalid_part("path", "invalid_path")
+}
+
+fn invalid_part(field: &str, code: &str) -> Error {
+    ApiError::InvalidRequest { fields: BTreeMap::from([(field.to_string(), vec![code.to_string()])]) }.into()
+}
@@ -10,15 +10,21 @@ mod modules;
 mod services;
 mod shared;
 mod middlewares;
+mod response;
 
-use modules::{config, database, mailer};
-use middlewares::jwt::AuthRequired;
-use services::{health_checker, authentication, account};
+use modules::{config, database, migrations, cli, mailer, i18n::I18n, jwt_keys::JwtKeys, rate_limiter::RateLimiter};
+use middlewares::jwt::RefreshCache;
+use middlewares::i18n::Localize;
+use api_schemas::validation::{json_error_handler, query_error_handler, path_error_handler};
 
 pub struct AppState {
     db: Pool<Postgres>,
     mailer: mailer::Mailer,
     config: config::Config,
+    refresh_cache: RefreshCache,
+    rate_limiter: RateLimiter,
+    i18n: I18n,
+    jwt_keys: JwtKeys,
 }
 
 #[actix_web::main]
@@ -30,12 +36,48 @@ async fn main() -> std::io::Result<()> {
 
     env_logger::init();
 
-    let config = config::Config::init();
-    let mailer = mailer::Mailer::new(&config);
+    let matches = config::command().get_matches();
+    let config = config::Config::init(&matches);
+    if let Some(("config", _)) = matches.subcommand() {
+        cli::print_config(&config);
+        return Ok(());
+    }
     let pool = database::init(&config).await;
 
+    match matches.subcommand() {
+        Some(("migrate", command)) => {
+            if let Err(err) = migrations::run(command, &pool).await {
+                println!("🔥 Failed to migrate the database: {}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+        Some((name, command)) => {
+            if let Err(err) = cli::run(name, command, &config, &pool).await {
+                println!("🔥 {}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+        None => ()
+    }
+    if config.migrate_on_startup {
+        if let Err(err) = migrations::up(&pool).await {
+            println!("🔥 Failed to migrate the database: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    let mailer = mailer::Mailer::new(&config);
+    let i18n = I18n::load(&config.locales_dir, &config.default_language);
+    let jwt_keys = JwtKeys::from_config(&config);
+    let rate_limiter = RateLimiter::new(&config, &pool);
+    rate_limiter.start_cleanup();
+    mailer.start_sender(pool.clone());
+    let refresh_cache = RefreshCache::new(std::time::Duration::from_secs(config.refresh_grace_seconds));
+
     let host = config.backend_host.clone();
-    let port = config.backend_port.clone();
+    let port = config.backend_port;
     let front_url = config.front_url.clone();
 
     println!("🚀 Server started successfully ({}:{})", &host, &port);
@@ -43,7 +85,7 @@ async fn main() -> std::io::Result<()> {
     HttpServer::new(move || {
         let cors = Cors::default()
             .allowed_origin(&front_url)
-            .allowed_methods(vec!["GET", "POST"])
+            .allowed_methods(vec!["GET", "POST", "DELETE"])
             .allowed_headers(vec![
                 header::CONTENT_TYPE,
                 header::AUTHORIZATION,
@@ -51,14 +93,24 @@ async fn main() -> std::io::Result<()> {
             ])
             .supports_credentials();
         App::new()
-            .app_data(web::Data::new(AppState { config: config.clone(), mailer: mailer.clone(), db: pool.clone() }))
+            .app_data(web::Data::new(AppState {
+                config: config.clone(),
+                mailer: mailer.clone(),
+                db: pool.clone(),
+                refresh_cache: refresh_cache.clone(),
+                rate_limiter: rate_limiter.clone(),
+                i18n: i18n.clone(),
+                jwt_keys: jwt_keys.clone()
+            }))
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+            .app_data(web::PathConfig::default().error_handler(path_error_handler))
+            .wrap(Localize)
             .wrap(cors)
-            .wrap(Logger::default())
-            .service(health_checker::init())
-            .service(authentication::init())
-            .service(web::scope("/api")
-                .wrap(AuthRequired)
-                .service(account::init()))
+            // Links carry their token in the query string, which is left out of the access log
+            .wrap(Logger::new(r#"%a "%{request_line}xi" %s %b "%{Referer}i" "%{User-Agent}i" %T"#)
+                .custom_request_replace("request_line", |req| format!("{} {} {:?}", req.method(), req.path(), req.version())))
+            .configure(services::configure)
     })
     .bind((host, port))?
     .run()
@@ -0,0 +1,29 @@
+use actix_web::{
+    body::EitherBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error };
+use std::{
+    rc::Rc,
+    future::{ready, Ready}
+};
+use crate::middlewares::i18n::LocalizeMiddleware;
+
+// Renders the `ApiError` responses in the language negotiated from the request
+pub struct Localize;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for Localize
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = LocalizeMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(LocalizeMiddleware { service: Rc::new(service) }))
+    }
+}
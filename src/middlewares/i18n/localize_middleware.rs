@@ -0,0 +1,57 @@
+use std::{
+    rc::Rc,
+    future::Future,
+    pin::Pin };
+use actix_web::{
+    web,
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse},
+    error::InternalError,
+    Error
+};
+
+use crate::shared::api_error::ApiError;
+use crate::AppState;
+
+type LocalBoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+pub struct LocalizeMiddleware<S> {
+    pub service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for LocalizeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let data = req.app_data::<web::Data<AppState>>().unwrap().clone();
+        let language = data.i18n.language(req.request());
+
+        Box::pin(async move {
+            match svc.call(req).await {
+                Ok(res) => {
+                    let localized = match res.response().error().and_then(|err| err.as_error::<ApiError>()) {
+                        Some(api_error) => api_error.localized_response(&data.i18n, &language),
+                        None => return Ok(res.map_into_left_body()),
+                    };
+                    let (request, _) = res.into_parts();
+                    Ok(ServiceResponse::new(request, localized).map_into_right_body())
+                },
+                // Errors of the inner middlewares carry their localized response up to the server
+                Err(err) => match err.as_error::<ApiError>() {
+                    Some(api_error) => Err(InternalError::from_response(api_error.to_string(), api_error.localized_response(&data.i18n, &language)).into()),
+                    None => Err(err),
+                },
+            }
+        })
+    }
+}
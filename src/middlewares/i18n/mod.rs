@@ -0,0 +1,5 @@
+mod localize;
+mod localize_middleware;
+
+pub use localize::Localize;
+pub use localize_middleware::LocalizeMiddleware;
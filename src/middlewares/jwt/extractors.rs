@@ -0,0 +1,32 @@
+use std::future::{ready, Ready};
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+
+use crate::middlewares::jwt::JwtToken;
+use crate::models::user::ROLE_ADMIN;
+use crate::shared::api_error::ApiError;
+
+// Claims of the authenticated user, only available behind `AuthRequired`
+impl FromRequest for JwtToken {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<JwtToken>().cloned().ok_or(ApiError::Unauthorized))
+    }
+}
+
+// Claims of an authenticated admin, rejects other users with a 403
+pub struct Admin(pub JwtToken);
+
+impl FromRequest for Admin {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(match req.extensions().get::<JwtToken>() {
+            None => Err(ApiError::Unauthorized),
+            Some(claims) if claims.has_role(ROLE_ADMIN) => Ok(Admin(claims.clone())),
+            Some(_) => Err(ApiError::Forbidden),
+        })
+    }
+}
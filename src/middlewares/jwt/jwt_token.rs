@@ -1,51 +1,83 @@
 use serde::{Deserialize, Serialize};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{EncodingKey, Header};
-use actix_web::cookie::Cookie;
+use actix_web::cookie::{time::Duration as ActixWebDuration, Cookie};
+
+use crate::models::user::ROLE_ADMIN;
+use crate::modules::jwt_keys::JwtKeys;
+use crate::shared::api_error::ApiError;
+
+// Lifetimes are bounded by the configuration, a date past the chrono range is still an error and not a panic
+fn expiration(now: chrono::DateTime<Utc>, ttl: std::time::Duration) -> Result<usize, ApiError> {
+    Duration::from_std(ttl).ok()
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .map(|exp| exp.timestamp() as usize)
+        .ok_or(ApiError::Internal)
+}
+
+// Kept in the payload so that the verifiers using the published keys can tell both tokens apart
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JwtToken {
     pub iat: usize,  // Auto validated - Optional. Issued at (as UTC timestamp)
     pub exp: usize,  // Required (validate_exp defaults to true in validation). Expiration time (as UTC timestamp)
+    pub typ: TokenType,
 
     pub id: uuid::Uuid,
     pub user_id: uuid::Uuid,
+    pub session_id: uuid::Uuid,
+    pub role: String,
 }
 
 impl JwtToken {
-    fn encode(&self, secret: &[u8]) -> String {
-        jsonwebtoken::encode(
-            &Header::default(),
-            &self,
-            &EncodingKey::from_secret(secret),
-        )
-        .unwrap()
+    pub fn encode(&self, keys: &JwtKeys) -> String {
+        keys.encode(self)
+    }
+
+    pub fn decode(value: &str, keys: &JwtKeys) -> Result<Self, jsonwebtoken::errors::Error> {
+        keys.decode::<JwtToken>(value)
     }
 
-    pub fn generate_access_token(user_id: uuid::Uuid) -> Self {
+    pub fn generate_access_token(user_id: uuid::Uuid, session_id: uuid::Uuid, role: String, ttl: std::time::Duration) -> Result<Self, ApiError> {
         let now = Utc::now();
-        JwtToken {
-            exp: (now + Duration::minutes(60)).timestamp() as usize,
+        Ok(JwtToken {
+            exp: expiration(now, ttl)?,
             iat: now.timestamp() as usize,
+            typ: TokenType::Access,
 
             id: uuid::Uuid::new_v4(),
-            user_id
-        }
+            user_id,
+            session_id,
+            role
+        })
     }
 
-    pub fn generate_refresh_token(user_id: uuid::Uuid) -> Self {
+    pub fn generate_refresh_token(user_id: uuid::Uuid, session_id: uuid::Uuid, role: String, ttl: std::time::Duration) -> Result<Self, ApiError> {
         let now = Utc::now();
-        JwtToken {
-            exp: (now + Duration::days(7)).timestamp() as usize,
+        Ok(JwtToken {
+            exp: expiration(now, ttl)?,
             iat: now.timestamp() as usize,
+            typ: TokenType::Refresh,
 
             id: uuid::Uuid::new_v4(),
-            user_id
-        }
+            user_id,
+            session_id,
+            role
+        })
     }
 
-    pub fn generate_cookie(&self, secret: &[u8], name: String) -> Cookie {
-        Cookie::build(name, self.encode(secret))
+    // Admins are granted every role
+    pub fn has_role(&self, role: &str) -> bool {
+        self.role == role || self.role == ROLE_ADMIN
+    }
+
+    pub fn generate_cookie(&self, keys: &JwtKeys, name: String) -> Cookie<'static> {
+        Cookie::build(name, self.encode(keys))
                 .path("/")
                 .secure(true)
                 .http_only(true)
@@ -60,4 +92,12 @@ impl JwtToken {
                 .finish()
     }
 
+    pub fn expired_cookie(name: String) -> Cookie<'static> {
+        Cookie::build(name, "")
+                .path("/")
+                .max_age(ActixWebDuration::new(-1, 0))
+                .http_only(true)
+                .finish()
+    }
+
 }
\ No newline at end of file
@@ -1,7 +1,19 @@
 mod jwt_middleware;
 mod jwt_token;
+mod unlock_token;
 mod auth_required;
+mod refresh_cache;
+mod refresh;
+mod role_required;
+mod role_middleware;
+mod extractors;
 
 pub use jwt_middleware::JwtMiddleware;
-pub use jwt_token::JwtToken;
-pub use auth_required::AuthRequired;
\ No newline at end of file
+pub use jwt_token::{JwtToken, TokenType};
+pub use unlock_token::UnlockToken;
+pub use auth_required::AuthRequired;
+pub use refresh_cache::{RefreshCache, RotatedPair, TokenPair};
+pub use refresh::refresh_tokens;
+pub use role_required::RoleRequired;
+pub use role_middleware::RoleMiddleware;
+pub use extractors::Admin;
\ No newline at end of file
@@ -0,0 +1,163 @@
+use chrono::prelude::*;
+use actix_web::HttpRequest;
+
+use crate::models::{User, Token, Session, SecurityEvent, security_event::REFRESH_TOKEN_REUSE};
+use crate::middlewares::jwt::{JwtToken, RotatedPair, TokenPair, TokenType};
+use crate::shared::{api_error::ApiError, tools::{get_user_agent, get_ip_address}};
+use crate::AppState;
+
+async fn rotate(claims: &JwtToken, data: &AppState) -> Result<RotatedPair, ApiError> {
+    log::debug!("Generate refreshed tokens");
+    // The role is read again so that promotions and demotions apply at the next refresh
+    let user = match User::get_user_from_id(claims.user_id, &data.db).await {
+        Ok(user) if !user.disabled => user,
+        Ok(_) | Err(sqlx::Error::RowNotFound) => return Err(ApiError::Unauthorized),
+        Err(err) => return Err(err.into())
+    };
+    let access_token = JwtToken::generate_access_token(claims.user_id, claims.session_id, user.role.clone(), data.config.jwt_expires_in)?;
+    let refresh_token = JwtToken::generate_refresh_token(claims.user_id, claims.session_id, user.role, data.config.jwt_refresh_expires_in)?;
+
+    Token::invalidate(claims.user_id, claims.id, &data.db).await?;
+    Token::remove_expired(claims.user_id, &data.db).await?;
+    Session::remove_orphans(claims.user_id, &data.db).await?;
+
+    Token::declare_new(access_token.user_id, access_token.id, access_token.session_id, Some(claims.id), DateTime::<Utc>::from_timestamp(access_token.exp as i64, 0).unwrap(), &data.db)
+        .await?;
+    Token::declare_new(refresh_token.user_id, refresh_token.id, refresh_token.session_id, Some(claims.id), DateTime::<Utc>::from_timestamp(refresh_token.exp as i64, 0).unwrap(), &data.db)
+        .await?;
+
+    Ok(RotatedPair {
+        refresh_token_id: refresh_token.id,
+        pair: TokenPair {
+            access: access_token.encode(&data.jwt_keys),
+            refresh: refresh_token.encode(&data.jwt_keys),
+        }
+    })
+}
+
+// Returns the refresh token claims and, unless another server instance already rotated it
+// within the grace window, the pair to send back to the client
+pub async fn refresh_tokens(refresh_token: &str, req: &HttpRequest, data: &AppState) -> Result<(JwtToken, Option<TokenPair>), ApiError> {
+    let claims = JwtToken::decode(refresh_token, &data.jwt_keys)
+        .ok()
+        .filter(|claims| claims.typ == TokenType::Refresh)
+        .ok_or(ApiError::Unauthorized)?;
+
+    let slot = data.refresh_cache.slot(claims.id);
+    let mut cached_pair = slot.lock().await;
+
+    if let Some(rotated) = cached_pair.as_ref() {
+        // A logout or a revocation within the grace window also applies to the cached pair
+        if !Token::is_valid(claims.user_id, rotated.refresh_token_id, &data.db).await? {
+            return Err(ApiError::Unauthorized);
+        }
+        log::debug!("Use already generated tokens");
+        return Ok((claims, Some(rotated.pair.clone())));
+    }
+
+    if Token::is_valid(claims.user_id, claims.id, &data.db).await? {
+        let rotated = rotate(&claims, data).await?;
+        let pair = rotated.pair.clone();
+        *cached_pair = Some(rotated);
+        return Ok((claims, Some(pair)));
+    }
+
+    if data.config.refresh_grace_shared {
+        let since = Utc::now() - chrono::Duration::seconds(data.config.refresh_grace_seconds as i64);
+        if Token::was_rotated_since(claims.user_id, claims.id, since, &data.db).await? {
+            log::debug!("Tokens already refreshed by another instance");
+            return Ok((claims, None));
+        }
+    }
+
+    // An already rotated refresh token is being replayed, the whole family is compromised
+    if Token::was_rotated(claims.user_id, claims.id, &data.db).await? {
+        Session::revoke(claims.user_id, claims.session_id, &data.db).await?;
+        SecurityEvent::record(claims.user_id, Some(claims.session_id), REFRESH_TOKEN_REUSE,
+            get_user_agent(req), get_ip_address(req, &data.config.trusted_proxies), &data.db)
+            .await?;
+    }
+    Err(ApiError::Unauthorized)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use actix_web::test::TestRequest;
+    use chrono::prelude::*;
+    use sqlx::{Pool, Postgres};
+
+    use super::refresh_tokens;
+    use crate::models::{User, Token, Session, security_event::REFRESH_TOKEN_REUSE};
+    use crate::middlewares::jwt::{JwtToken, RefreshCache};
+    use crate::modules::{config::{test_matches, Config}, mailer::Mailer, i18n::I18n, jwt_keys::JwtKeys, rate_limiter::RateLimiter};
+    use crate::shared::api_error::ApiError;
+    use crate::AppState;
+
+    fn state(grace: Duration, db: &Pool<Postgres>) -> AppState {
+        let mut config = Config::load(&test_matches(&[])).unwrap();
+        config.refresh_grace_shared = false;
+        AppState {
+            mailer: Mailer::new(&config),
+            refresh_cache: RefreshCache::new(grace),
+            rate_limiter: RateLimiter::new(&config, db),
+            i18n: I18n::load(&config.locales_dir, &config.default_language),
+            jwt_keys: JwtKeys::from_config(&config),
+            db: db.clone(),
+            config,
+        }
+    }
+
+    // Logs a new user in and returns its session id and encoded refresh token
+    async fn login(data: &AppState) -> (User, uuid::Uuid, String) {
+        let user = User::create_user("reuse@example.com".to_string(), "en".to_string(), &data.db).await.unwrap();
+        let session = Session::create(user.id, None, None, &data.db).await.unwrap();
+        let refresh_token = JwtToken::generate_refresh_token(user.id, session.id, user.role.clone(), data.config.jwt_refresh_expires_in).unwrap();
+        Token::declare_new(user.id, refresh_token.id, session.id, None, DateTime::<Utc>::from_timestamp(refresh_token.exp as i64, 0).unwrap(), &data.db)
+            .await
+            .unwrap();
+        (user, session.id, refresh_token.encode(&data.jwt_keys))
+    }
+
+    async fn reuse_events(user: &User, db: &Pool<Postgres>) -> i64 {
+        sqlx::query_scalar("SELECT count(*) FROM security_events WHERE user_id = $1 AND kind = $2")
+            .bind(user.id)
+            .bind(REFRESH_TOKEN_REUSE)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn concurrent_refreshes_within_the_grace_window_share_the_pair(db: Pool<Postgres>) {
+        let data = state(Duration::from_secs(30), &db);
+        let (user, _, refresh_token) = login(&data).await;
+        let req = TestRequest::default().to_http_request();
+
+        let (_, first) = refresh_tokens(&refresh_token, &req, &data).await.unwrap();
+        let (_, second) = refresh_tokens(&refresh_token, &req, &data).await.unwrap();
+
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.access, second.access);
+        assert_eq!(first.refresh, second.refresh);
+        assert_eq!(reuse_events(&user, &db).await, 0);
+    }
+
+    #[sqlx::test]
+    async fn replayed_refresh_token_revokes_its_family(db: Pool<Postgres>) {
+        let data = state(Duration::ZERO, &db);
+        let (user, session_id, refresh_token) = login(&data).await;
+        let req = TestRequest::default().to_http_request();
+
+        let (_, rotated) = refresh_tokens(&refresh_token, &req, &data).await.unwrap();
+        let rotated = JwtToken::decode(&rotated.unwrap().refresh, &data.jwt_keys).unwrap();
+        assert!(Token::is_valid(user.id, rotated.id, &db).await.unwrap());
+
+        let replay = refresh_tokens(&refresh_token, &req, &data).await;
+
+        assert!(matches!(replay, Err(ApiError::Unauthorized)));
+        assert!(!Token::is_valid(user.id, rotated.id, &db).await.unwrap());
+        assert!(!Session::get_active_from_user(user.id, &db).await.unwrap().iter().any(|session| session.id == session_id));
+        assert_eq!(reuse_events(&user, &db).await, 1);
+    }
+}
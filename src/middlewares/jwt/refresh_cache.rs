@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant} };
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct TokenPair {
+    pub access: String,
+    pub refresh: String,
+}
+
+// A pair issued by a rotation, with the id of its refresh token to check it was not revoked since
+#[derive(Debug, Clone)]
+pub struct RotatedPair {
+    pub refresh_token_id: Uuid,
+    pub pair: TokenPair,
+}
+
+type TokenPairSlot = Arc<tokio::sync::Mutex<Option<RotatedPair>>>;
+
+// Remembers, for a short grace window, the pair issued when a refresh token was rotated.
+// Concurrent requests carrying the same refresh token wait on the slot and reuse that pair
+// instead of being seen as a replay. Cloning shares the cache between actix workers.
+#[derive(Clone)]
+pub struct RefreshCache {
+    grace: Duration,
+    slots: Arc<Mutex<HashMap<Uuid, (Instant, TokenPairSlot)>>>,
+}
+
+impl RefreshCache {
+    pub fn new(grace: Duration) -> RefreshCache {
+        RefreshCache {
+            grace,
+            slots: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn slot(&self, refresh_token_id: Uuid) -> TokenPairSlot {
+        let now = Instant::now();
+        let mut slots = self.slots.lock().unwrap();
+
+        slots.retain(|_, (created_at, _)| now.duration_since(*created_at) < self.grace);
+        let (_, slot) = slots.entry(refresh_token_id)
+            .or_insert_with(|| (now, Arc::new(tokio::sync::Mutex::new(None))));
+        Arc::clone(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+    use uuid::Uuid;
+
+    use super::{RefreshCache, RotatedPair, TokenPair};
+
+    fn rotated() -> RotatedPair {
+        RotatedPair {
+            refresh_token_id: Uuid::new_v4(),
+            pair: TokenPair { access: "access".to_string(), refresh: "refresh".to_string() },
+        }
+    }
+
+    #[test]
+    fn same_refresh_token_shares_its_slot() {
+        let cache = RefreshCache::new(Duration::from_secs(30));
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(Arc::ptr_eq(&cache.slot(first), &cache.slot(first)));
+        assert!(!Arc::ptr_eq(&cache.slot(first), &cache.slot(second)));
+        // Clones are handed to every worker
+        assert!(Arc::ptr_eq(&cache.slot(first), &cache.clone().slot(first)));
+    }
+
+    #[test]
+    fn slots_are_forgotten_after_the_grace_window() {
+        let cache = RefreshCache::new(Duration::ZERO);
+        let id = Uuid::new_v4();
+
+        assert!(!Arc::ptr_eq(&cache.slot(id), &cache.slot(id)));
+    }
+
+    #[actix_web::test]
+    async fn concurrent_requests_get_the_pair_of_the_first_rotation() {
+        let cache = RefreshCache::new(Duration::from_secs(30));
+        let id = Uuid::new_v4();
+
+        let slot = cache.slot(id);
+        let mut first = slot.lock().await;
+        let waiting = cache.slot(id);
+        assert!(waiting.try_lock().is_err());
+
+        let pair = rotated();
+        *first = Some(pair.clone());
+        drop(first);
+
+        let second = waiting.lock().await;
+        assert_eq!(second.as_ref().map(|rotated| rotated.refresh_token_id), Some(pair.refresh_token_id));
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    rc::Rc,
+    future::Future,
+    pin::Pin };
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse},
+    Error,
+    HttpMessage
+};
+
+use crate::middlewares::jwt::JwtToken;
+use crate::shared::api_error::ApiError;
+
+type LocalBoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+pub struct RoleMiddleware<S> {
+    pub service: Rc<S>,
+    pub role: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for RoleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let allowed = req.extensions().get::<JwtToken>().map(|claims| claims.has_role(self.role));
+
+        Box::pin(async move {
+            match allowed {
+                None => Err(ApiError::Unauthorized.into()),
+                Some(false) => Err(ApiError::Forbidden.into()),
+                Some(true) => svc.call(req).await,
+            }
+        })
+    }
+}
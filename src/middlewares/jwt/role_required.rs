@@ -0,0 +1,28 @@
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error };
+use std::{
+    rc::Rc,
+    future::{ready, Ready}
+};
+use crate::middlewares::jwt::RoleMiddleware;
+
+// Must be wrapped inside `AuthRequired`, it only checks the claims the latter extracted
+pub struct RoleRequired(pub &'static str);
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for RoleRequired
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RoleMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RoleMiddleware { service: Rc::new(service), role: self.0 }))
+    }
+}
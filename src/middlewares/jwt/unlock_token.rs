@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use chrono::Utc;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+
+// Signed payload of an unlock link, only usable for the lock it was issued for
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnlockToken {
+    pub iat: usize,
+    pub exp: usize,
+
+    pub user_id: uuid::Uuid,
+    pub lock_count: i16,
+}
+
+impl UnlockToken {
+    pub fn new(user_id: uuid::Uuid, lock_count: i16, exp: usize) -> Self {
+        UnlockToken {
+            iat: Utc::now().timestamp() as usize,
+            exp,
+
+            user_id,
+            lock_count
+        }
+    }
+
+    pub fn encode(&self, secret: &[u8]) -> String {
+        jsonwebtoken::encode(
+            &Header::default(),
+            &self,
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+
+    pub fn decode(value: &str, secret: &[u8]) -> Result<Self, jsonwebtoken::errors::Error> {
+        jsonwebtoken::decode::<UnlockToken>(
+            value,
+            &DecodingKey::from_secret(secret),
+            &Validation::default(),
+        )
+        .map(|data| data.claims)
+    }
+}
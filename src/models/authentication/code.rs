@@ -1,41 +1,137 @@
 use chrono::prelude::*;
+use chrono::Duration;
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
-use sqlx::{Postgres, Pool, FromRow, Error};
+use sha2::Sha256;
+use sqlx::{Postgres, Pool, PgExecutor, FromRow, Error};
 
-use crate::shared::tools::generate_string_number;
+use crate::modules::config::CodeAlphabet;
+use crate::shared::tools::{generate_string_number, generate_string_alphanumeric};
+
+type HmacSha256 = Hmac<Sha256>;
 
 #[derive(Debug, Deserialize, FromRow, Serialize, Clone)]
 pub struct Code {
     pub id: uuid::Uuid,
+    // Hex encoded HMAC-SHA256 of the code sent to the user
     pub code: String,
     pub tries: i16,
     pub emitted_at: DateTime<Utc>,
 }
 
+fn keyed_hash(code: &str, secret: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).unwrap();
+    mac.update(code.as_bytes());
+    mac
+}
+
 impl Code {
-    pub async fn create_code(id: uuid::Uuid, db: &Pool<Postgres>) -> Result<Code, Error> {
-        sqlx::query_as!(
-            Code,
-            "INSERT INTO codes (id, code) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET code = EXCLUDED.code, tries = 0, emitted_at = DEFAULT RETURNING *",
+    // Returns the clear code, only its keyed hash is stored
+    pub async fn create_code<'e, E: PgExecutor<'e>>(id: uuid::Uuid, length: u8, alphabet: CodeAlphabet, secret: &[u8], db: E) -> Result<String, Error> {
+        let code = match alphabet {
+            CodeAlphabet::Numeric => generate_string_number(length),
+            CodeAlphabet::Alphanumeric => generate_string_alphanumeric(length),
+        };
+        sqlx::query!(
+            "INSERT INTO codes (id, code) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET code = EXCLUDED.code, tries = 0, emitted_at = DEFAULT",
             id,
-            &generate_string_number(6),
+            hex::encode(keyed_hash(&code, secret).finalize().into_bytes()),
         )
-            .fetch_one(db)
+            .execute(db)
             .await
+            .map(|_| code)
     }
 
-    pub async fn get_code_from_id(id: uuid::Uuid, db: &Pool<Postgres>) -> Option<Code> {
+    pub async fn get_code_from_id(id: uuid::Uuid, db: &Pool<Postgres>) -> Result<Option<Code>, Error> {
         sqlx::query_as!(Code, "SELECT * FROM codes WHERE id = $1", id)
             .fetch_optional(db)
             .await
-            .unwrap()
     }
 
-    pub async fn add_try(id: uuid::Uuid, db: &Pool<Postgres>) -> i16 {
+    // Constant time comparison of a candidate against the stored hash, codes are case insensitive
+    pub fn matches(&self, candidate: &str, secret: &[u8]) -> bool {
+        match hex::decode(&self.code) {
+            Ok(expected) => keyed_hash(&candidate.trim().to_uppercase(), secret).verify_slice(&expected).is_ok(),
+            Err(_) => false
+        }
+    }
+
+    pub fn is_alive(&self, ttl_minutes: i64) -> bool {
+        Utc::now() - Duration::minutes(ttl_minutes) <= self.emitted_at
+    }
+
+    // Returns the number of deleted codes
+    pub async fn purge_expired(ttl_minutes: i64, db: &Pool<Postgres>) -> Result<u64, Error> {
+        sqlx::query!("DELETE FROM codes WHERE emitted_at < $1", Utc::now() - Duration::minutes(ttl_minutes))
+            .execute(db)
+            .await
+            .map(|res| res.rows_affected())
+    }
+
+    // None when the user has no pending code, it was consumed, purged or never sent
+    pub async fn add_try(id: uuid::Uuid, db: &Pool<Postgres>) -> Result<Option<i16>, Error> {
         sqlx::query!("UPDATE codes SET tries = tries + 1 WHERE id = $1 RETURNING tries", id)
-            .fetch_one(db)
+            .fetch_optional(db)
             .await
-            .map(|res| res.tries)
-            .unwrap()
+            .map(|res| res.map(|res| res.tries))
+    }
+
+    pub async fn consume<'e, E: PgExecutor<'e>>(id: uuid::Uuid, db: E) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM codes WHERE id = $1", id)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use hmac::Mac;
+
+    use super::{keyed_hash, Code};
+
+    fn stored(code: &str, secret: &[u8]) -> Code {
+        Code {
+            id: uuid::Uuid::nil(),
+            code: hex::encode(keyed_hash(code, secret).finalize().into_bytes()),
+            tries: 0,
+            emitted_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn only_the_keyed_hash_is_stored() {
+        let code = stored("123456", b"secret");
+        assert_eq!(code.code.len(), 64);
+        assert!(!code.code.contains("123456"));
+        assert_ne!(code.code, stored("123456", b"other secret").code);
+    }
+
+    #[test]
+    fn matches_the_code_it_was_created_from() {
+        let code = stored("AB23CD", b"secret");
+
+        assert!(code.matches("AB23CD", b"secret"));
+        assert!(code.matches(" ab23cd ", b"secret"));
+        assert!(!code.matches("AB23CE", b"secret"));
+        assert!(!code.matches("AB23CD", b"other secret"));
+        assert!(!code.matches("", b"secret"));
+    }
+
+    #[test]
+    fn malformed_hashes_never_match() {
+        let mut code = stored("123456", b"secret");
+        code.code = "123456".to_string();
+        assert!(!code.matches("123456", b"secret"));
+    }
+
+    #[test]
+    fn codes_expire_after_their_ttl() {
+        let mut code = stored("123456", b"secret");
+        assert!(code.is_alive(5));
+
+        code.emitted_at = Utc::now() - Duration::minutes(6);
+        assert!(!code.is_alive(5));
     }
 }
\ No newline at end of file
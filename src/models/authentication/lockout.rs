@@ -0,0 +1,113 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Pool, PgExecutor, FromRow, Error};
+use uuid::Uuid;
+
+// Codes exhausted (max tries reached) by a user within the current window, and the resulting locks
+#[derive(Debug, Deserialize, FromRow, Serialize, Clone)]
+pub struct Lockout {
+    pub user_id: Uuid,
+    pub exhausted_codes: i16,
+    pub window_started_at: DateTime<Utc>,
+    pub lock_count: i16,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl Lockout {
+    pub async fn get_from_user(user_id: Uuid, db: &Pool<Postgres>) -> Result<Option<Lockout>, Error> {
+        sqlx::query_as!(Lockout, "SELECT * FROM lockouts WHERE user_id = $1", user_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked_until.is_some_and(|locked_until| locked_until > Utc::now())
+    }
+
+    // Cooldown of the next lock, doubling with every previous one up to `max_minutes`
+    pub fn cooldown_minutes(&self, base_minutes: i64, max_minutes: i64) -> i64 {
+        base_minutes
+            .saturating_mul(1i64 << self.lock_count.clamp(0, 30))
+            .min(max_minutes)
+    }
+
+    // Starts a new window when the previous one is over
+    pub async fn record_exhausted_code(user_id: Uuid, window_minutes: i32, db: &Pool<Postgres>) -> Result<Lockout, Error> {
+        sqlx::query_as!(
+            Lockout,
+            "INSERT INTO lockouts (user_id, exhausted_codes) VALUES ($1, 1)
+             ON CONFLICT (user_id) DO UPDATE SET
+                exhausted_codes = CASE WHEN lockouts.window_started_at < now() - make_interval(mins => $2)
+                    THEN 1 ELSE lockouts.exhausted_codes + 1 END,
+                window_started_at = CASE WHEN lockouts.window_started_at < now() - make_interval(mins => $2)
+                    THEN now() ELSE lockouts.window_started_at END
+             RETURNING *",
+            user_id,
+            window_minutes,
+        )
+            .fetch_one(db)
+            .await
+    }
+
+    pub async fn lock<'e, E: PgExecutor<'e>>(user_id: Uuid, locked_until: DateTime<Utc>, db: E) -> Result<Lockout, Error> {
+        sqlx::query_as!(
+            Lockout,
+            "UPDATE lockouts SET locked_until = $2, lock_count = lock_count + 1, exhausted_codes = 0 WHERE user_id = $1 RETURNING *",
+            user_id,
+            locked_until,
+        )
+            .fetch_one(db)
+            .await
+    }
+
+    // The lock count is kept so that the next cooldown is still longer
+    pub async fn unlock(user_id: Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
+        sqlx::query!("UPDATE lockouts SET locked_until = NULL, exhausted_codes = 0 WHERE user_id = $1",
+            user_id)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn clear(user_id: Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM lockouts WHERE user_id = $1",
+            user_id)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use super::Lockout;
+
+    fn lockout(lock_count: i16) -> Lockout {
+        Lockout { user_id: Uuid::nil(), exhausted_codes: 0, window_started_at: Utc::now(), lock_count, locked_until: None }
+    }
+
+    #[test]
+    fn cooldown_doubles_with_every_lock_up_to_the_maximum() {
+        let cooldowns: Vec<i64> = (0..5).map(|lock_count| lockout(lock_count).cooldown_minutes(15, 1440)).collect();
+        assert_eq!(cooldowns, [15, 30, 60, 120, 240]);
+
+        assert_eq!(lockout(7).cooldown_minutes(15, 1440), 1440);
+        assert_eq!(lockout(i16::MAX).cooldown_minutes(15, 1440), 1440);
+        assert_eq!(lockout(i16::MAX).cooldown_minutes(i64::MAX, i64::MAX), i64::MAX);
+    }
+
+    #[test]
+    fn locked_until_the_end_of_the_cooldown() {
+        let mut lockout = lockout(1);
+        assert!(!lockout.is_locked());
+
+        lockout.locked_until = Some(Utc::now() + Duration::minutes(1));
+        assert!(lockout.is_locked());
+
+        lockout.locked_until = Some(Utc::now() - Duration::minutes(1));
+        assert!(!lockout.is_locked());
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Postgres, Pool, PgExecutor, FromRow, Error};
+use uuid::Uuid;
+
+const TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, Deserialize, FromRow, Serialize, Clone)]
+pub struct MagicLink {
+    // Hex encoded SHA-256 of the token sent to the user
+    pub token_hash: String,
+    pub user_id: Uuid,
+    pub redirect_path: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+impl MagicLink {
+    // Returns the clear token and replaces the previous links of the user
+    pub async fn create<'e, E: PgExecutor<'e>>(user_id: Uuid, redirect_path: Option<String>, ttl_minutes: i64, db: E) -> Result<String, Error> {
+        let mut bytes = [0u8; TOKEN_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+
+        sqlx::query!(
+            "WITH previous AS (DELETE FROM magic_links WHERE user_id = $2)
+             INSERT INTO magic_links (token_hash, user_id, redirect_path, expires_at) VALUES ($1, $2, $3, $4)",
+            hash(&token),
+            user_id,
+            redirect_path,
+            Utc::now() + Duration::minutes(ttl_minutes),
+        )
+            .execute(db)
+            .await
+            .map(|_| token)
+    }
+
+    pub fn is_well_formed(token: &str) -> bool {
+        URL_SAFE_NO_PAD.decode(token).is_ok_and(|bytes| bytes.len() == TOKEN_BYTES)
+    }
+
+    // Deleting the link as it is read makes it single use, even for concurrent requests
+    pub async fn consume(token: &str, db: &Pool<Postgres>) -> Result<Option<MagicLink>, Error> {
+        sqlx::query_as!(MagicLink, "DELETE FROM magic_links WHERE token_hash = $1 RETURNING *", hash(token))
+            .fetch_optional(db)
+            .await
+            .map(|link| link.filter(|link| link.expires_at > Utc::now()))
+    }
+
+    // Returns the number of deleted links
+    pub async fn purge_expired(db: &Pool<Postgres>) -> Result<u64, Error> {
+        sqlx::query!("DELETE FROM magic_links WHERE expires_at < now()")
+            .execute(db)
+            .await
+            .map(|res| res.rows_affected())
+    }
+}
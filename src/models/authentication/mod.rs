@@ -1,7 +1,15 @@
 pub mod user;
 pub mod code;
 pub mod token;
+pub mod session;
+pub mod security_event;
+pub mod lockout;
+pub mod magic_link;
 
 pub use user::User;
 pub use code::Code;
-pub use token::Token;
\ No newline at end of file
+pub use token::Token;
+pub use session::Session;
+pub use security_event::SecurityEvent;
+pub use lockout::Lockout;
+pub use magic_link::MagicLink;
\ No newline at end of file
@@ -0,0 +1,37 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, FromRow, Error};
+use uuid::Uuid;
+
+pub const REFRESH_TOKEN_REUSE: &str = "refresh_token_reuse";
+pub const ACCOUNT_LOCKED: &str = "account_locked";
+
+#[derive(Debug, Deserialize, FromRow, Serialize, Clone)]
+pub struct SecurityEvent {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub session_id: Option<Uuid>,
+    pub kind: String,
+
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+}
+
+impl SecurityEvent {
+    pub async fn record<'e, E: PgExecutor<'e>>(user_id: Uuid, session_id: Option<Uuid>, kind: &str, user_agent: Option<String>, ip_address: Option<String>, db: E) -> Result<SecurityEvent, Error> {
+        println!("🚨 Security event {} for user {} (session {:?})", kind, user_id, session_id);
+        sqlx::query_as!(
+            SecurityEvent,
+            "INSERT INTO security_events (user_id, session_id, kind, user_agent, ip_address) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+            user_id,
+            session_id,
+            kind,
+            user_agent,
+            ip_address,
+        )
+            .fetch_one(db)
+            .await
+    }
+}
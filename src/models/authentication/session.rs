@@ -0,0 +1,70 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Pool, FromRow, Error};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, FromRow, Serialize, Clone)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+impl Session {
+    pub async fn create(user_id: Uuid, user_agent: Option<String>, ip_address: Option<String>, db: &Pool<Postgres>) -> Result<Session, Error> {
+        sqlx::query_as!(
+            Session,
+            "INSERT INTO sessions (user_id, user_agent, ip_address) VALUES ($1, $2, $3) RETURNING *",
+            user_id,
+            user_agent,
+            ip_address,
+        )
+            .fetch_one(db)
+            .await
+    }
+
+    // A session is active as long as one of its tokens can still be used
+    pub async fn get_active_from_user(user_id: Uuid, db: &Pool<Postgres>) -> Result<Vec<Session>, Error> {
+        sqlx::query_as!(
+            Session,
+            "SELECT * FROM sessions WHERE user_id = $1 AND EXISTS
+                (SELECT 1 FROM tokens WHERE tokens.session_id = sessions.id AND tokens.is_valid AND tokens.expiration > now())
+             ORDER BY last_used_at DESC",
+            user_id,
+        )
+            .fetch_all(db)
+            .await
+    }
+
+    // Only write when the last recorded use is older than a minute to avoid an update on every request
+    pub async fn touch(id: Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
+        sqlx::query!("UPDATE sessions SET last_used_at = now() WHERE id = $1 AND last_used_at < now() - interval '1 minute'",
+            id)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+
+    // Returns false when the user has no valid token left in this session
+    pub async fn revoke(user_id: Uuid, id: Uuid, db: &Pool<Postgres>) -> Result<bool, Error> {
+        sqlx::query!("UPDATE tokens SET is_valid = false WHERE user_id = $1 AND session_id = $2 AND is_valid",
+            user_id,
+            id)
+            .execute(db)
+            .await
+            .map(|res| res.rows_affected() > 0)
+    }
+
+    pub async fn remove_orphans(user_id: Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM sessions WHERE user_id = $1 AND NOT EXISTS (SELECT 1 FROM tokens WHERE tokens.session_id = sessions.id)",
+            user_id)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+}
@@ -7,33 +7,65 @@ use uuid::Uuid;
 pub struct Token {
     pub user_id: Uuid,
     pub token_id: Uuid,
+    pub session_id: Uuid,
+    // Refresh token this one was rotated from, all tokens of a session form one family
+    pub parent_id: Option<Uuid>,
 
     pub expiration: DateTime<Utc>,
-    pub is_valid: bool
+    pub is_valid: bool,
+    pub created_at: DateTime<Utc>
 }
 
 impl Token {
-    pub async fn declare_new(user_id: Uuid, token_id: Uuid, expiration: DateTime<Utc>, db: &Pool<Postgres>) -> Result<Token, Error> {
+    pub async fn declare_new(user_id: Uuid, token_id: Uuid, session_id: Uuid, parent_id: Option<Uuid>, expiration: DateTime<Utc>, db: &Pool<Postgres>) -> Result<Token, Error> {
         sqlx::query_as!(
             Token,
-            "INSERT INTO tokens (user_id, token_id, expiration) VALUES ($1, $2, $3) RETURNING *",
+            "INSERT INTO tokens (user_id, token_id, session_id, parent_id, expiration) VALUES ($1, $2, $3, $4, $5) RETURNING *",
             user_id,
             token_id,
+            session_id,
+            parent_id,
             expiration,
         )
             .fetch_one(db)
             .await
     }
 
+    pub async fn get_from_user(user_id: Uuid, db: &Pool<Postgres>) -> Result<Vec<Token>, Error> {
+        sqlx::query_as!(Token, "SELECT * FROM tokens WHERE user_id = $1 ORDER BY created_at DESC", user_id)
+            .fetch_all(db)
+            .await
+    }
+
+    // Deleted tokens, with their user or by a migration, are no longer valid
     pub async fn is_valid(user_id: Uuid, token_id: Uuid, db: &Pool<Postgres>) -> Result<bool, Error> {
         sqlx::query!("SELECT is_valid FROM tokens WHERE user_id = $1 AND token_id = $2",
+            user_id,
+            token_id)
+            .fetch_optional(db)
+            .await
+            .map(|row| row.is_some_and(|row| row.is_valid))
+    }
+
+
+    pub async fn was_rotated(user_id: Uuid, token_id: Uuid, db: &Pool<Postgres>) -> Result<bool, Error> {
+        sqlx::query!("SELECT EXISTS(SELECT 1 FROM tokens WHERE user_id = $1 AND parent_id = $2) AS \"rotated!\"",
             user_id,
             token_id)
             .fetch_one(db)
             .await
-            .map(|row| row.is_valid)
+            .map(|row| row.rotated)
     }
 
+    pub async fn was_rotated_since(user_id: Uuid, token_id: Uuid, since: DateTime<Utc>, db: &Pool<Postgres>) -> Result<bool, Error> {
+        sqlx::query!("SELECT EXISTS(SELECT 1 FROM tokens WHERE user_id = $1 AND parent_id = $2 AND created_at >= $3) AS \"rotated!\"",
+            user_id,
+            token_id,
+            since)
+            .fetch_one(db)
+            .await
+            .map(|row| row.rotated)
+    }
 
     pub async fn remove_expired(user_id: Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
         sqlx::query!("DELETE FROM tokens WHERE user_id = $1 AND expiration < now()",
@@ -43,6 +75,14 @@ impl Token {
             .map(|_| ())
     }
 
+    // Returns the number of deleted tokens
+    pub async fn purge_expired(db: &Pool<Postgres>) -> Result<u64, Error> {
+        sqlx::query!("DELETE FROM tokens WHERE expiration < now()")
+            .execute(db)
+            .await
+            .map(|res| res.rows_affected())
+    }
+
     pub async fn invalidate(user_id: Uuid, token_id: Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
         sqlx::query!("UPDATE tokens SET is_valid = false WHERE user_id = $1 AND token_id = $2",
             user_id,
@@ -52,4 +92,12 @@ impl Token {
             .map(|_| ())
     }
 
+    pub async fn invalidate_all(user_id: Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
+        sqlx::query!("UPDATE tokens SET is_valid = false WHERE user_id = $1",
+            user_id)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+
 }
\ No newline at end of file
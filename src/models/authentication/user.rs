@@ -1,33 +1,48 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 use sqlx::{Postgres, Pool, FromRow, Row, Error};
+use utoipa::ToSchema;
 
-#[derive(Debug, Deserialize, FromRow, Serialize, Clone)]
+pub const ROLE_USER: &str = "user";
+pub const ROLE_ADMIN: &str = "admin";
+pub const ROLES: [&str; 2] = [ROLE_USER, ROLE_ADMIN];
+
+#[derive(Debug, Deserialize, FromRow, Serialize, Clone, ToSchema)]
 pub struct User {
     pub id: uuid::Uuid,
     pub email: String,
     pub language_id: String,
     pub role: String,
     pub verified: bool,
+    pub disabled: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+// The email filter matches a substring, its wildcard characters are taken literally
+fn like_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
 impl User {
-    pub async fn is_user_exist(email: String, db: &Pool<Postgres>) -> bool {
+    pub async fn is_user_exist(email: String, db: &Pool<Postgres>) -> Result<bool, Error> {
         sqlx::query("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
             .bind(email)
             .fetch_one(db)
             .await
-            .unwrap()
-            .get(0)
+            .map(|row| row.get(0))
     }
 
-    pub async fn get_user_from_email(email: String, db: &Pool<Postgres>) -> Option<User> {
+    pub async fn get_user_from_email(email: String, db: &Pool<Postgres>) -> Result<Option<User>, Error> {
         sqlx::query_as!(User, "SELECT * FROM users WHERE email = $1", email)
             .fetch_optional(db)
             .await
-            .unwrap()
+    }
+
+    pub async fn get_user_from_id(id: uuid::Uuid, db: &Pool<Postgres>) -> Result<User, Error> {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(db)
+            .await
     }
 
     pub async fn create_user(email: String, language: String, db: &Pool<Postgres>) -> Result<User, Error> {
@@ -36,7 +51,7 @@ impl User {
             "INSERT INTO users (email, language_id, role, verified) VALUES ($1, $2, $3, $4) RETURNING *",
             email.to_lowercase(),
             language.to_lowercase(),
-            "user".to_string(),
+            ROLE_USER.to_string(),
             false
         )
             .fetch_one(db)
@@ -52,4 +67,74 @@ impl User {
             .fetch_one(db)
             .await
     }
+
+    pub async fn get_users(email: Option<String>, role: Option<String>, verified: Option<bool>, limit: i64, offset: i64, db: &Pool<Postgres>) -> Result<Vec<User>, Error> {
+        sqlx::query_as!(
+            User,
+            "SELECT * FROM users
+             WHERE ($1::TEXT IS NULL OR email LIKE '%' || $1 || '%' ESCAPE '\\')
+               AND ($2::TEXT IS NULL OR role = $2)
+               AND ($3::BOOLEAN IS NULL OR verified = $3)
+             ORDER BY created_at DESC, email
+             LIMIT $4 OFFSET $5",
+            email.map(|email| like_escape(&email.to_lowercase())),
+            role,
+            verified,
+            limit,
+            offset
+        )
+            .fetch_all(db)
+            .await
+    }
+
+    pub async fn count_users(email: Option<String>, role: Option<String>, verified: Option<bool>, db: &Pool<Postgres>) -> Result<i64, Error> {
+        sqlx::query!(
+            "SELECT COUNT(*) AS \"count!\" FROM users
+             WHERE ($1::TEXT IS NULL OR email LIKE '%' || $1 || '%' ESCAPE '\\')
+               AND ($2::TEXT IS NULL OR role = $2)
+               AND ($3::BOOLEAN IS NULL OR verified = $3)",
+            email.map(|email| like_escape(&email.to_lowercase())),
+            role,
+            verified
+        )
+            .fetch_one(db)
+            .await
+            .map(|row| row.count)
+    }
+
+    pub async fn set_role(id: uuid::Uuid, role: String, db: &Pool<Postgres>) -> Result<User, Error> {
+        sqlx::query_as!(
+            User,
+            "UPDATE users SET role = $2, updated_at = NOW() WHERE id = $1 RETURNING *",
+            id,
+            role
+        )
+            .fetch_one(db)
+            .await
+    }
+
+    pub async fn set_disabled(id: uuid::Uuid, disabled: bool, db: &Pool<Postgres>) -> Result<User, Error> {
+        sqlx::query_as!(
+            User,
+            "UPDATE users SET disabled = $2, updated_at = NOW() WHERE id = $1 RETURNING *",
+            id,
+            disabled
+        )
+            .fetch_one(db)
+            .await
+    }
+
+    pub async fn delete(id: uuid::Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
+        let mut transaction = db.begin().await?;
+
+        sqlx::query!("DELETE FROM tokens WHERE user_id = $1", id).execute(&mut *transaction).await?;
+        sqlx::query!("DELETE FROM sessions WHERE user_id = $1", id).execute(&mut *transaction).await?;
+        sqlx::query!("DELETE FROM codes WHERE id = $1", id).execute(&mut *transaction).await?;
+        sqlx::query!("DELETE FROM security_events WHERE user_id = $1", id).execute(&mut *transaction).await?;
+        sqlx::query!("DELETE FROM lockouts WHERE user_id = $1", id).execute(&mut *transaction).await?;
+        sqlx::query!("DELETE FROM magic_links WHERE user_id = $1", id).execute(&mut *transaction).await?;
+        sqlx::query!("DELETE FROM users WHERE id = $1", id).execute(&mut *transaction).await?;
+
+        transaction.commit().await
+    }
 }
\ No newline at end of file
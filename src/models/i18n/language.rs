@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Pool, FromRow, Error};
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, FromRow, Serialize, Clone, ToSchema)]
+pub struct Language {
+    pub code: String,
+    pub name: String,
+}
+
+impl Language {
+    pub async fn get_languages(db: &Pool<Postgres>) -> Result<Vec<Language>, Error> {
+        sqlx::query_as!(Language, "SELECT * FROM languages ORDER BY code")
+            .fetch_all(db)
+            .await
+    }
+}
@@ -0,0 +1,3 @@
+pub mod language;
+
+pub use language::Language;
@@ -0,0 +1,100 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Pool, PgExecutor, FromRow, Error};
+use uuid::Uuid;
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_SENT: &str = "sent";
+pub const STATUS_FAILED: &str = "failed";
+
+// Claimed messages are hidden from other senders for this long, then retried if still pending
+const CLAIM_LEASE_SECONDS: f64 = 300.0;
+
+#[derive(Debug, Deserialize, FromRow, Serialize, Clone)]
+pub struct OutboxMessage {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+    pub html_body: Option<String>,
+
+    pub status: String,
+    pub attempts: i16,
+    pub last_error: Option<String>,
+
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+impl OutboxMessage {
+    pub async fn enqueue<'e, E: PgExecutor<'e>>(recipient: String, subject: String, body: String, html_body: Option<String>, db: E) -> Result<OutboxMessage, Error> {
+        sqlx::query_as!(
+            OutboxMessage,
+            "INSERT INTO outbox (recipient, subject, body, html_body) VALUES ($1, $2, $3, $4) RETURNING *",
+            recipient,
+            subject,
+            body,
+            html_body,
+        )
+            .fetch_one(db)
+            .await
+    }
+
+    // Skips rows claimed by a concurrent sender
+    pub async fn claim_due(limit: i64, db: &Pool<Postgres>) -> Result<Vec<OutboxMessage>, Error> {
+        sqlx::query_as!(
+            OutboxMessage,
+            "UPDATE outbox SET next_attempt_at = now() + make_interval(secs => $2)
+             WHERE id IN (
+                SELECT id FROM outbox WHERE status = $3 AND next_attempt_at <= now()
+                ORDER BY next_attempt_at LIMIT $1 FOR UPDATE SKIP LOCKED)
+             RETURNING *",
+            limit,
+            CLAIM_LEASE_SECONDS,
+            STATUS_PENDING,
+        )
+            .fetch_all(db)
+            .await
+    }
+
+    // The bodies carry one-time codes and login links, they are cleared once the message is done with
+    pub async fn mark_sent(id: Uuid, db: &Pool<Postgres>) -> Result<(), Error> {
+        sqlx::query!("UPDATE outbox SET status = $2, attempts = attempts + 1, last_error = NULL, sent_at = now(), body = '', html_body = NULL WHERE id = $1",
+            id,
+            STATUS_SENT)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn mark_failed(id: Uuid, error: &str, db: &Pool<Postgres>) -> Result<(), Error> {
+        sqlx::query!("UPDATE outbox SET status = $2, attempts = attempts + 1, last_error = $3, body = '', html_body = NULL WHERE id = $1",
+            id,
+            STATUS_FAILED,
+            error)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+
+    // A message still pending after the lifetime of the code or link it carries is useless
+    pub async fn purge_stale(ttl_minutes: i64, db: &Pool<Postgres>) -> Result<u64, Error> {
+        sqlx::query!("DELETE FROM outbox WHERE status = $1 AND created_at < $2",
+            STATUS_PENDING,
+            Utc::now() - chrono::Duration::minutes(ttl_minutes))
+            .execute(db)
+            .await
+            .map(|res| res.rows_affected())
+    }
+
+    pub async fn retry_at(id: Uuid, error: &str, next_attempt_at: DateTime<Utc>, db: &Pool<Postgres>) -> Result<(), Error> {
+        sqlx::query!("UPDATE outbox SET attempts = attempts + 1, last_error = $2, next_attempt_at = $3 WHERE id = $1",
+            id,
+            error,
+            next_attempt_at)
+            .execute(db)
+            .await
+            .map(|_| ())
+    }
+}
@@ -1,3 +1,7 @@
 mod authentication;
+mod mail;
+mod i18n;
 
-pub use authentication::*;
\ No newline at end of file
+pub use authentication::*;
+pub use mail::*;
+pub use i18n::*;
\ No newline at end of file
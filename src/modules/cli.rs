@@ -0,0 +1,148 @@
+use std::error::Error;
+use chrono::Utc;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use sqlx::{Postgres, Pool};
+
+use crate::config::Config;
+use crate::models::{User, Token, Code, MagicLink, OutboxMessage};
+use crate::models::user::{ROLES, ROLE_ADMIN};
+use crate::modules::mailer::Mailer;
+use crate::modules::rate_limiter::RateLimiter;
+
+fn email_arg() -> Arg {
+    Arg::new("email").value_name("EMAIL").required(true)
+}
+
+fn role_arg() -> Arg {
+    Arg::new("role").long("role").value_name("ROLE").value_parser(ROLES)
+}
+
+// Operator commands, they share the settings of the server
+pub fn commands() -> [Command; 5] {
+    [
+        Command::new("user")
+            .about("Manage the users")
+            .subcommand_required(true)
+            .subcommand(Command::new("create").about("Create a user")
+                .arg(email_arg())
+                .arg(Arg::new("language").long("language").value_name("LANGUAGE").help("Defaults to DEFAULT_LANGUAGE"))
+                .arg(role_arg().default_value(ROLES[0]))
+                .arg(Arg::new("verified").long("verified").action(ArgAction::SetTrue).help("Mark the email as verified")))
+            .subcommand(Command::new("verify").about("Mark the email of a user as verified").arg(email_arg()))
+            .subcommand(Command::new("promote").about("Change the role of a user")
+                .arg(email_arg())
+                .arg(role_arg().default_value(ROLE_ADMIN))),
+        Command::new("token")
+            .about("Manage the access and refresh tokens")
+            .subcommand_required(true)
+            .subcommand(Command::new("list").about("List the access and refresh tokens of a user").arg(email_arg()))
+            .subcommand(Command::new("revoke").about("Revoke the access and refresh tokens of a user")
+                .arg(email_arg())
+                .arg(Arg::new("token-id").long("token-id").value_name("UUID").value_parser(clap::value_parser!(uuid::Uuid))
+                    .help("Only revoke this token, every token of the user otherwise"))),
+        Command::new("purge").about("Delete the expired tokens, codes and login links, the stale pending emails, and the rate limits of the postgres store that are full again"),
+        Command::new("send-test-email").about("Send an email right away to check the mail settings").arg(email_arg()),
+        Command::new("config").about("Print the effective configuration, secrets are redacted"),
+    ]
+}
+
+async fn find_user(command: &ArgMatches, db: &Pool<Postgres>) -> Result<User, Box<dyn Error>> {
+    let email = command.get_one::<String>("email").unwrap().trim().to_lowercase();
+    User::get_user_from_email(email.clone(), db).await?
+        .ok_or_else(|| format!("No user with the email {}", email).into())
+}
+
+async fn user(command: &ArgMatches, config: &Config, db: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    match command.subcommand() {
+        Some(("create", command)) => {
+            let email = command.get_one::<String>("email").unwrap().trim().to_lowercase();
+            if !validator::validate_email(&email) {
+                return Err(format!("{} is not a valid email", email).into());
+            }
+            if User::is_user_exist(email.clone(), db).await? {
+                return Err(format!("A user with the email {} already exists", email).into());
+            }
+            let language = command.get_one::<String>("language").unwrap_or(&config.default_language);
+
+            let mut user = User::create_user(email, language.clone(), db).await?;
+            let role = command.get_one::<String>("role").unwrap();
+            if user.role != *role {
+                user = User::set_role(user.id, role.clone(), db).await?;
+            }
+            if command.get_flag("verified") {
+                user = User::set_email_verified(user.id, db).await?;
+            }
+            println!("✅ User {} created with the id {}", user.email, user.id);
+        },
+        Some(("verify", command)) => {
+            let user = User::set_email_verified(find_user(command, db).await?.id, db).await?;
+            println!("✅ The email of {} is verified", user.email);
+        },
+        Some(("promote", command)) => {
+            let role = command.get_one::<String>("role").unwrap();
+            let user = User::set_role(find_user(command, db).await?.id, role.clone(), db).await?;
+            Token::invalidate_all(user.id, db).await?;
+            println!("✅ {} now has the role {}, their tokens are revoked", user.email, user.role);
+        },
+        _ => unreachable!("the user subcommand is required")
+    }
+    Ok(())
+}
+
+async fn token(command: &ArgMatches, db: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    match command.subcommand() {
+        Some(("list", command)) => {
+            let user = find_user(command, db).await?;
+            let tokens = Token::get_from_user(user.id, db).await?;
+            for token in &tokens {
+                let state = if !token.is_valid { "revoked" } else if token.expiration < Utc::now() { "expired" } else { "valid" };
+                println!("{} session {} created {} expires {} {}", token.token_id, token.session_id, token.created_at, token.expiration, state);
+            }
+            println!("{} token(s) for {}", tokens.len(), user.email);
+        },
+        Some(("revoke", command)) => {
+            let user = find_user(command, db).await?;
+            match command.get_one::<uuid::Uuid>("token-id") {
+                Some(token_id) => {
+                    Token::invalidate(user.id, *token_id, db).await?;
+                    println!("✅ Token {} of {} revoked", token_id, user.email);
+                },
+                None => {
+                    Token::invalidate_all(user.id, db).await?;
+                    println!("✅ Every token of {} revoked", user.email);
+                }
+            }
+        },
+        _ => unreachable!("the token subcommand is required")
+    }
+    Ok(())
+}
+
+pub fn print_config(config: &Config) {
+    println!("{:#?}", config);
+}
+
+// Runs an operator command, `config` is handled before connecting to the database
+pub async fn run(name: &str, command: &ArgMatches, config: &Config, db: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    match name {
+        "user" => user(command, config, db).await,
+        "token" => token(command, db).await,
+        "purge" => {
+            let tokens = Token::purge_expired(db).await?;
+            let codes = Code::purge_expired(config.code_ttl_minutes, db).await?;
+            let magic_links = MagicLink::purge_expired(db).await?;
+            let emails = OutboxMessage::purge_stale(config.code_ttl_minutes, db).await?;
+            let rate_limits = RateLimiter::new(config, db).purge_postgres(db).await?;
+            println!("✅ Deleted {} expired token(s), {} expired code(s), {} expired login link(s), {} stale email(s) and {} rate limit(s)",
+                tokens, codes, magic_links, emails, rate_limits);
+            Ok(())
+        },
+        "send-test-email" => {
+            let email = command.get_one::<String>("email").unwrap();
+            Mailer::new(config).send_test(email).await.map_err(|e| e as Box<dyn Error>)?;
+            println!("✅ Test email sent to {}", email);
+            Ok(())
+        },
+        _ => unreachable!("unknown subcommand {}", name)
+    }
+}
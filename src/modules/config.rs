@@ -1,3 +1,40 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    net::IpAddr,
+    str::FromStr,
+    time::Duration };
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use jsonwebtoken::Algorithm;
+use lettre::Address;
+use sqlx::postgres::{PgConnectOptions, PgSslMode};
+
+use crate::modules::cli;
+use crate::modules::rate_limiter::RateLimitStoreKind;
+use crate::modules::mail_transport::MailTransportKind;
+use crate::modules::openapi::ApiDocsUi;
+use crate::shared::secret::Secret;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CodeAlphabet {
+    #[default]
+    Numeric,
+    Alphanumeric,
+}
+
+impl FromStr for CodeAlphabet {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "numeric" => Ok(CodeAlphabet::Numeric),
+            "alphanumeric" => Ok(CodeAlphabet::Alphanumeric),
+            _ => Err(format!("Unknown code alphabet {}, expected numeric or alphanumeric", value))
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub app_name: String,
@@ -5,51 +42,457 @@ pub struct Config {
     pub postgres_host: String,
     pub postgres_port: u16,
     pub postgres_user: String,
-    pub postgres_pwd: String,
+    pub postgres_pwd: Secret,
     pub postgres_db: String,
 
-    pub jwt_secret: String,
-    pub jwt_expires_in: String,
-    pub jwt_maxage: i32,
+    pub database_url: Option<Secret>,
+    pub database_ssl_mode: Option<PgSslMode>,
+    pub database_ssl_root_cert: Option<String>,
+    pub database_max_connections: u32,
+    pub database_min_connections: u32,
+    pub database_acquire_timeout: Duration,
+    pub database_idle_timeout: Duration,
+    pub database_statement_timeout: Duration,
+    pub database_connect_retries: u32,
+    pub database_connect_retry_delay: Duration,
+    pub migrate_on_startup: bool,
+
+    pub jwt_secret: Secret,
+    pub jwt_algorithm: Algorithm,
+    pub jwt_key_id: String,
+    pub jwt_private_key_file: String,
+    pub jwt_public_keys_dir: String,
+    pub jwt_expires_in: Duration,
+    pub jwt_refresh_expires_in: Duration,
+
+    pub refresh_grace_seconds: u64,
+    pub refresh_grace_shared: bool,
 
     pub max_tries: i16,
+    pub code_secret: Secret,
+    pub code_length: u8,
+    pub code_alphabet: CodeAlphabet,
+    pub code_ttl_minutes: i64,
+
+    pub lockout_threshold: i16,
+    pub lockout_window_minutes: i32,
+    pub lockout_base_minutes: i64,
+    pub lockout_max_minutes: i64,
 
     pub backend_host: String,
     pub backend_port: u16,
+    pub backend_url: String,
+    pub trusted_proxies: Vec<IpAddr>,
 
     pub front_url: String,
 
+    pub api_docs_ui: ApiDocsUi,
+
+    pub rate_limit_store: RateLimitStoreKind,
+    pub rate_limit_email_capacity: f64,
+    pub rate_limit_email_per_hour: f64,
+    pub rate_limit_ip_capacity: f64,
+    pub rate_limit_ip_per_hour: f64,
+
+    pub default_language: String,
+    pub locales_dir: String,
+
+    pub mail_transport: MailTransportKind,
+    pub mail_from: String,
+    pub mail_templates_dir: String,
+    pub mail_file_dir: String,
     pub mail_host: String,
     pub mail_port: u16,
     pub mail_auth_user: String,
-    pub mail_auth_pwd: String
+    pub mail_auth_pwd: Secret,
+    pub mail_max_attempts: i16,
+    pub mail_retry_base_seconds: i64,
+    pub mail_poll_seconds: u64
 }
 
-fn get_field(name: &str) -> String {
-    std::env::var(name).expect(format!("{} must be set in .env file", name).as_str())
+// Every setting, read from the CONFIG_FILE TOML file (lowercase key), then from the environment and
+// finally from the command line (--kebab-case flag), the last source defining it wins. Secrets can
+// instead be read from the file named by their _FILE variant (Docker and Kubernetes secrets)
+const SETTINGS: &[&str] = &[
+    "APP_NAME",
+    "POSTGRES_HOST", "POSTGRES_PORT", "POSTGRES_USER", "POSTGRES_PASSWORD", "POSTGRES_PASSWORD_FILE", "POSTGRES_DB",
+    "DATABASE_URL", "DATABASE_URL_FILE", "DATABASE_SSL_MODE", "DATABASE_SSL_ROOT_CERT",
+    "DATABASE_MAX_CONNECTIONS", "DATABASE_MIN_CONNECTIONS", "DATABASE_ACQUIRE_TIMEOUT", "DATABASE_IDLE_TIMEOUT",
+    "DATABASE_STATEMENT_TIMEOUT", "DATABASE_CONNECT_RETRIES", "DATABASE_CONNECT_RETRY_DELAY",
+    "MIGRATE_ON_STARTUP",
+    "JWT_SECRET", "JWT_SECRET_FILE", "JWT_ALGORITHM", "JWT_KEY_ID", "JWT_PRIVATE_KEY_FILE", "JWT_PUBLIC_KEYS_DIR",
+    "JWT_EXPIRED_IN", "JWT_REFRESH_EXPIRED_IN",
+    "REFRESH_GRACE_SECONDS", "REFRESH_GRACE_SHARED",
+    "MAX_TRIES", "CODE_SECRET", "CODE_SECRET_FILE", "CODE_LENGTH", "CODE_ALPHABET", "CODE_TTL_MINUTES",
+    "LOCKOUT_THRESHOLD", "LOCKOUT_WINDOW_MINUTES", "LOCKOUT_BASE_MINUTES", "LOCKOUT_MAX_MINUTES",
+    "BACKEND_HOST", "BACKEND_PORT", "BACKEND_URL", "TRUSTED_PROXIES",
+    "FRONT_URL",
+    "API_DOCS_UI",
+    "RATE_LIMIT_STORE", "RATE_LIMIT_EMAIL_CAPACITY", "RATE_LIMIT_EMAIL_PER_HOUR", "RATE_LIMIT_IP_CAPACITY", "RATE_LIMIT_IP_PER_HOUR",
+    "DEFAULT_LANGUAGE", "LOCALES_DIR",
+    "MAIL_TRANSPORT", "MAIL_FROM", "MAIL_TEMPLATES_DIR", "MAIL_FILE_DIR", "MAIL_HOST", "MAIL_PORT", "MAIL_AUTH_USER", "MAIL_AUTH_PWD", "MAIL_AUTH_PWD_FILE",
+    "MAIL_MAX_ATTEMPTS", "MAIL_RETRY_BASE_SECONDS", "MAIL_POLL_SECONDS",
+    "CONFIG_FILE",
+];
+
+// Lowest layer, only the secrets and the settings to reach the database, the mail server and the
+// front application have to be given. BACKEND_URL defaults to the BACKEND_HOST and BACKEND_PORT URL
+const DEFAULTS: &[(&str, &str)] = &[
+    ("APP_NAME", env!("CARGO_PKG_NAME")),
+    ("POSTGRES_PORT", "5432"),
+    ("DATABASE_MAX_CONNECTIONS", "10"), ("DATABASE_MIN_CONNECTIONS", "0"), ("DATABASE_ACQUIRE_TIMEOUT", "30s"),
+    ("DATABASE_IDLE_TIMEOUT", "10m"), ("DATABASE_STATEMENT_TIMEOUT", "30s"), ("DATABASE_CONNECT_RETRIES", "5"),
+    ("DATABASE_CONNECT_RETRY_DELAY", "1s"),
+    ("MIGRATE_ON_STARTUP", "false"),
+    ("JWT_ALGORITHM", "HS256"), ("JWT_KEY_ID", "main"), ("JWT_PRIVATE_KEY_FILE", "./keys/private.pem"),
+    ("JWT_PUBLIC_KEYS_DIR", "./keys/public"), ("JWT_EXPIRED_IN", "60m"), ("JWT_REFRESH_EXPIRED_IN", "7d"),
+    ("REFRESH_GRACE_SECONDS", "30"), ("REFRESH_GRACE_SHARED", "false"),
+    ("MAX_TRIES", "3"), ("CODE_LENGTH", "6"), ("CODE_ALPHABET", "numeric"), ("CODE_TTL_MINUTES", "5"),
+    ("LOCKOUT_THRESHOLD", "3"), ("LOCKOUT_WINDOW_MINUTES", "60"), ("LOCKOUT_BASE_MINUTES", "15"), ("LOCKOUT_MAX_MINUTES", "1440"),
+    ("BACKEND_HOST", "127.0.0.1"), ("BACKEND_PORT", "8000"), ("TRUSTED_PROXIES", ""),
+    ("API_DOCS_UI", "none"),
+    ("RATE_LIMIT_STORE", "memory"), ("RATE_LIMIT_EMAIL_CAPACITY", "3"), ("RATE_LIMIT_EMAIL_PER_HOUR", "10"),
+    ("RATE_LIMIT_IP_CAPACITY", "20"), ("RATE_LIMIT_IP_PER_HOUR", "100"),
+    ("DEFAULT_LANGUAGE", "en"), ("LOCALES_DIR", "./locales"),
+    ("MAIL_TRANSPORT", "smtp"), ("MAIL_TEMPLATES_DIR", "./templates/mail"), ("MAIL_FILE_DIR", "./mails"),
+    ("MAIL_MAX_ATTEMPTS", "5"), ("MAIL_RETRY_BASE_SECONDS", "30"), ("MAIL_POLL_SECONDS", "2"),
+];
+
+// Upper bounds of the lifetimes and rates, a year and a million
+const MAX_LIFETIME: Duration = Duration::from_secs(365 * 24 * 3600);
+const MAX_LIFETIME_MINUTES: i64 = 365 * 24 * 60;
+const MAX_CODE_TTL_MINUTES: i64 = 24 * 60;
+const MAX_RATE: f64 = 1_000_000.0;
+
+fn flag(name: &str) -> String {
+    name.to_lowercase().replace('_', "-")
 }
 
+pub fn command() -> Command {
+    let command = Command::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Passwordless authentication server, every setting can also be set in the environment or in CONFIG_FILE")
+        .arg(Arg::new("migrate").long("migrate").action(ArgAction::SetTrue)
+            .help("Apply the pending migrations before starting the server, like MIGRATE_ON_STARTUP"))
+        .subcommand(Command::new("migrate")
+            .about("Manage the database migrations embedded in the binary")
+            .subcommand_required(true)
+            .subcommand(Command::new("status").about("List the migrations and whether they are applied"))
+            .subcommand(Command::new("up").about("Apply the pending migrations"))
+            .subcommand(Command::new("down").about("Revert the latest applied migration")))
+        .subcommands(cli::commands());
+    SETTINGS.iter().fold(command, |command, name| {
+        command.arg(Arg::new(*name).long(flag(name)).value_name("VALUE").env(*name).hide_env(true))
+    })
+}
+
+// Collects every missing or malformed setting instead of stopping at the first one
+struct Loader {
+    file: HashMap<String, String>,
+    matches: ArgMatches,
+    errors: Vec<String>,
+    // Settings that may be missing, they then take their default value
+    optional_settings: Vec<&'static str>,
+}
+
+impl Loader {
+    fn new(matches: ArgMatches) -> Loader {
+        let mut loader = Loader { file: HashMap::new(), matches, errors: vec![], optional_settings: vec![] };
+
+        if let Some(path) = loader.matches.get_one::<String>("CONFIG_FILE").cloned() {
+            match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|content| content.parse::<toml::Table>().map_err(|e| e.to_string())) {
+                Ok(table) => for (key, value) in table {
+                    let name = key.to_uppercase();
+                    if !SETTINGS.contains(&name.as_str()) {
+                        loader.errors.push(format!("{}: unknown setting {}", path, key));
+                        continue;
+                    }
+                    let value = match value {
+                        toml::Value::String(value) => value,
+                        value => value.to_string(),
+                    };
+                    loader.file.insert(name, value);
+                },
+                Err(e) => loader.errors.push(format!("CONFIG_FILE: {} cannot be read: {}", path, e)),
+            }
+        }
+        loader
+    }
+
+    // The command line and the environment are both parsed by clap, the file and then the defaults
+    // only fill the gaps
+    fn optional(&self, name: &str) -> Option<String> {
+        self.matches.get_one::<String>(name).cloned()
+            .or_else(|| self.file.get(name).cloned())
+            .or_else(|| DEFAULTS.iter().find(|(setting, _)| *setting == name).map(|(_, value)| value.to_string()))
+    }
+
+    fn missing<T: Default>(&mut self, name: &str) -> T {
+        if !self.optional_settings.contains(&name) {
+            self.errors.push(format!("{} is missing", name));
+        }
+        T::default()
+    }
+
+    fn string(&mut self, name: &str) -> String {
+        match self.optional(name) {
+            Some(value) => value,
+            None => self.missing(name)
+        }
+    }
+
+    // Defaults only stand in for the invalid settings until the report is printed
+    fn parse_with<T: Default, E: Display>(&mut self, name: &str, parser: impl Fn(&str) -> Result<T, E>) -> T {
+        match self.optional(name) {
+            None => self.missing(name),
+            Some(value) => parser(value.trim()).unwrap_or_else(|e| {
+                self.errors.push(format!("{} has an invalid value \"{}\": {}", name, value, e));
+                T::default()
+            })
+        }
+    }
+
+    fn parse<T: FromStr + Default>(&mut self, name: &str) -> T where T::Err: Display {
+        self.parse_with(name, |value| value.parse::<T>())
+    }
+
+    fn optional_parse<T: FromStr + Default>(&mut self, name: &str) -> Option<T> where T::Err: Display {
+        self.optional(name).is_some().then(|| self.parse(name))
+    }
+
+    // Durations are written like 90s, 60m, 12h or 7d
+    fn duration(&mut self, name: &str) -> Duration {
+        self.parse_with(name, humantime::parse_duration)
+    }
+
+    // Trailing newlines of secret files are not part of the secret
+    fn optional_secret(&mut self, name: &str) -> Option<Secret> {
+        let file_name = format!("{}_FILE", name);
+        match (self.optional(name), self.optional(&file_name)) {
+            (Some(_), Some(_)) => {
+                self.errors.push(format!("{} and {} are both set", name, file_name));
+                Some(Secret::default())
+            },
+            (None, Some(path)) => match fs::read_to_string(&path) {
+                Ok(content) => Some(Secret::from_str(content.trim_end_matches(['\r', '\n'])).unwrap()),
+                Err(e) => {
+                    self.errors.push(format!("{} {} cannot be read: {}", file_name, path, e));
+                    Some(Secret::default())
+                }
+            },
+            (value, None) => value.map(|value| Secret::from_str(&value).unwrap())
+        }
+    }
+
+    fn secret(&mut self, name: &str) -> Secret {
+        match self.optional_secret(name) {
+            Some(secret) => secret,
+            None => self.missing(name)
+        }
+    }
+
+    fn check(&mut self, valid: bool, message: &str) {
+        if !valid {
+            self.errors.push(message.to_string());
+        }
+    }
+}
 
 impl Config {
-    pub fn init() -> Config {
-        Config {
-            app_name: get_field("APP_NAME"),
-            postgres_host: get_field("POSTGRES_HOST"),
-            postgres_port: get_field("POSTGRES_PORT").parse::<u16>().unwrap(),
-            postgres_user: get_field("POSTGRES_USER"),
-            postgres_pwd: get_field("POSTGRES_PASSWORD"),
-            postgres_db: get_field("POSTGRES_DB"),
-            jwt_secret: get_field("JWT_SECRET"),
-            jwt_expires_in: get_field("JWT_EXPIRED_IN"),
-            jwt_maxage: get_field("JWT_MAXAGE").parse::<i32>().unwrap(),
-            max_tries: get_field("MAX_TRIES").parse::<i16>().unwrap(),
-            backend_host: get_field("BACKEND_HOST"),
-            backend_port: get_field("BACKEND_PORT").parse::<u16>().unwrap(),
-            front_url: get_field("FRONT_URL"),
-            mail_host: get_field("MAIL_HOST"),
-            mail_port: get_field("MAIL_PORT").parse::<u16>().unwrap(),
-            mail_auth_user: get_field("MAIL_AUTH_USER"),
-            mail_auth_pwd: get_field("MAIL_AUTH_PWD")
+    pub fn init(matches: &ArgMatches) -> Config {
+        Config::load(matches).unwrap_or_else(|errors| {
+            println!("🔥 Invalid configuration:");
+            for error in errors {
+                println!("  - {}", error);
+            }
+            std::process::exit(1);
+        })
+    }
+
+    pub fn load(matches: &ArgMatches) -> Result<Config, Vec<String>> {
+        let mut loader = Loader::new(matches.clone());
+
+        // The separate Postgres settings are only needed without DATABASE_URL
+        let database_url = loader.optional_secret("DATABASE_URL");
+        if let Some(url) = &database_url {
+            loader.optional_settings.extend(["POSTGRES_HOST", "POSTGRES_PORT", "POSTGRES_USER", "POSTGRES_PASSWORD", "POSTGRES_DB"]);
+            if let Err(e) = PgConnectOptions::from_str(url.expose()) {
+                loader.errors.push(format!("DATABASE_URL is invalid: {}", e));
+            }
+        }
+
+        // The server settings are only read by the SMTP transports, plain ones may skip authentication.
+        // MAIL_FROM defaults to MAIL_AUTH_USER, which used to be the sender address
+        let mail_transport: MailTransportKind = loader.parse("MAIL_TRANSPORT");
+        let uses_smtp = matches!(mail_transport, MailTransportKind::Smtp | MailTransportKind::Plain);
+        if mail_transport != MailTransportKind::Smtp {
+            loader.optional_settings.extend(["MAIL_AUTH_USER", "MAIL_AUTH_PWD"]);
+        }
+        let mail_from = match loader.optional("MAIL_FROM").or_else(|| loader.optional("MAIL_AUTH_USER")) {
+            Some(value) => value.parse::<Address>().map(|_| value.clone()).unwrap_or_else(|e| {
+                loader.errors.push(format!("MAIL_FROM has an invalid value \"{}\": {}", value, e));
+                String::default()
+            }),
+            None => loader.missing("MAIL_FROM")
+        };
+
+        let backend_host = loader.string("BACKEND_HOST");
+        let backend_port = loader.parse("BACKEND_PORT");
+        let backend_url = loader.optional("BACKEND_URL").unwrap_or_else(|| format!("http://{}:{}", backend_host, backend_port));
+
+        let config = Config {
+            app_name: loader.string("APP_NAME"),
+            postgres_host: loader.string("POSTGRES_HOST"),
+            postgres_port: loader.parse("POSTGRES_PORT"),
+            postgres_user: loader.string("POSTGRES_USER"),
+            postgres_pwd: loader.secret("POSTGRES_PASSWORD"),
+            postgres_db: loader.string("POSTGRES_DB"),
+            database_url,
+            database_ssl_mode: loader.optional_parse("DATABASE_SSL_MODE"),
+            database_ssl_root_cert: loader.optional("DATABASE_SSL_ROOT_CERT"),
+            database_max_connections: loader.parse("DATABASE_MAX_CONNECTIONS"),
+            database_min_connections: loader.parse("DATABASE_MIN_CONNECTIONS"),
+            database_acquire_timeout: loader.duration("DATABASE_ACQUIRE_TIMEOUT"),
+            database_idle_timeout: loader.duration("DATABASE_IDLE_TIMEOUT"),
+            database_statement_timeout: loader.duration("DATABASE_STATEMENT_TIMEOUT"),
+            database_connect_retries: loader.parse("DATABASE_CONNECT_RETRIES"),
+            database_connect_retry_delay: loader.duration("DATABASE_CONNECT_RETRY_DELAY"),
+            migrate_on_startup: loader.parse::<bool>("MIGRATE_ON_STARTUP") || matches.get_flag("migrate"),
+            jwt_secret: loader.secret("JWT_SECRET"),
+            jwt_algorithm: loader.parse("JWT_ALGORITHM"),
+            jwt_key_id: loader.string("JWT_KEY_ID"),
+            jwt_private_key_file: loader.string("JWT_PRIVATE_KEY_FILE"),
+            jwt_public_keys_dir: loader.string("JWT_PUBLIC_KEYS_DIR"),
+            jwt_expires_in: loader.duration("JWT_EXPIRED_IN"),
+            jwt_refresh_expires_in: loader.duration("JWT_REFRESH_EXPIRED_IN"),
+            refresh_grace_seconds: loader.parse("REFRESH_GRACE_SECONDS"),
+            refresh_grace_shared: loader.parse("REFRESH_GRACE_SHARED"),
+            max_tries: loader.parse("MAX_TRIES"),
+            code_secret: loader.secret("CODE_SECRET"),
+            code_length: loader.parse("CODE_LENGTH"),
+            code_alphabet: loader.parse("CODE_ALPHABET"),
+            code_ttl_minutes: loader.parse("CODE_TTL_MINUTES"),
+            lockout_threshold: loader.parse("LOCKOUT_THRESHOLD"),
+            lockout_window_minutes: loader.parse("LOCKOUT_WINDOW_MINUTES"),
+            lockout_base_minutes: loader.parse("LOCKOUT_BASE_MINUTES"),
+            lockout_max_minutes: loader.parse("LOCKOUT_MAX_MINUTES"),
+            backend_host,
+            backend_port,
+            backend_url,
+            trusted_proxies: loader.parse_with("TRUSTED_PROXIES", |value| value.split(',')
+                .map(str::trim)
+                .filter(|ip| !ip.is_empty())
+                .map(|ip| ip.parse::<IpAddr>().map_err(|e| format!("{}: {}", ip, e)))
+                .collect::<Result<Vec<IpAddr>, String>>()),
+            front_url: loader.string("FRONT_URL"),
+            api_docs_ui: loader.parse("API_DOCS_UI"),
+            rate_limit_store: loader.parse("RATE_LIMIT_STORE"),
+            rate_limit_email_capacity: loader.parse("RATE_LIMIT_EMAIL_CAPACITY"),
+            rate_limit_email_per_hour: loader.parse("RATE_LIMIT_EMAIL_PER_HOUR"),
+            rate_limit_ip_capacity: loader.parse("RATE_LIMIT_IP_CAPACITY"),
+            rate_limit_ip_per_hour: loader.parse("RATE_LIMIT_IP_PER_HOUR"),
+            default_language: loader.string("DEFAULT_LANGUAGE").to_lowercase(),
+            locales_dir: loader.string("LOCALES_DIR"),
+            mail_transport,
+            mail_from,
+            mail_templates_dir: loader.string("MAIL_TEMPLATES_DIR"),
+            mail_file_dir: loader.string("MAIL_FILE_DIR"),
+            mail_host: if uses_smtp { loader.string("MAIL_HOST") } else { String::default() },
+            mail_port: if uses_smtp { loader.parse("MAIL_PORT") } else { u16::default() },
+            mail_auth_user: if uses_smtp { loader.string("MAIL_AUTH_USER") } else { String::default() },
+            mail_auth_pwd: if uses_smtp { loader.secret("MAIL_AUTH_PWD") } else { Secret::default() },
+            mail_max_attempts: loader.parse("MAIL_MAX_ATTEMPTS"),
+            mail_retry_base_seconds: loader.parse("MAIL_RETRY_BASE_SECONDS"),
+            mail_poll_seconds: loader.parse("MAIL_POLL_SECONDS")
+        };
+
+        // Request bodies accept codes of 4 to 32 characters
+        loader.check((4..=32).contains(&config.code_length), "CODE_LENGTH must be between 4 and 32");
+        loader.check(config.database_max_connections > 0, "DATABASE_MAX_CONNECTIONS must be positive");
+        loader.check(config.database_min_connections <= config.database_max_connections, "DATABASE_MIN_CONNECTIONS must not exceed DATABASE_MAX_CONNECTIONS");
+        loader.check(config.max_tries > 0, "MAX_TRIES must be positive");
+        loader.check(config.jwt_expires_in < config.jwt_refresh_expires_in, "JWT_EXPIRED_IN must be shorter than JWT_REFRESH_EXPIRED_IN");
+        loader.check(config.lockout_base_minutes <= config.lockout_max_minutes, "LOCKOUT_BASE_MINUTES must not exceed LOCKOUT_MAX_MINUTES");
+        loader.check(config.rate_limit_email_capacity >= 1.0 && config.rate_limit_ip_capacity >= 1.0, "RATE_LIMIT_EMAIL_CAPACITY and RATE_LIMIT_IP_CAPACITY must be at least 1");
+        loader.check(config.rate_limit_email_per_hour > 0.0 && config.rate_limit_ip_per_hour > 0.0, "RATE_LIMIT_EMAIL_PER_HOUR and RATE_LIMIT_IP_PER_HOUR must be positive");
+        loader.check(config.mail_max_attempts > 0, "MAIL_MAX_ATTEMPTS must be positive");
+        // Lifetimes are turned into dates, the upper bounds keep them in range
+        loader.check(!config.jwt_expires_in.is_zero(), "JWT_EXPIRED_IN must be positive");
+        loader.check(config.jwt_refresh_expires_in <= MAX_LIFETIME, "JWT_REFRESH_EXPIRED_IN must not exceed a year");
+        loader.check(config.refresh_grace_seconds <= config.jwt_expires_in.as_secs(), "REFRESH_GRACE_SECONDS must not exceed JWT_EXPIRED_IN");
+        loader.check((1..=MAX_CODE_TTL_MINUTES).contains(&config.code_ttl_minutes), "CODE_TTL_MINUTES must be between 1 and 1440");
+        loader.check((1..=MAX_LIFETIME_MINUTES as i32).contains(&config.lockout_window_minutes), "LOCKOUT_WINDOW_MINUTES must be between 1 and 525600");
+        loader.check(config.lockout_base_minutes >= 1, "LOCKOUT_BASE_MINUTES must be positive");
+        loader.check(config.lockout_max_minutes <= MAX_LIFETIME_MINUTES, "LOCKOUT_MAX_MINUTES must not exceed 525600");
+        loader.check(config.rate_limit_email_capacity <= MAX_RATE && config.rate_limit_ip_capacity <= MAX_RATE,
+            "RATE_LIMIT_EMAIL_CAPACITY and RATE_LIMIT_IP_CAPACITY must not exceed 1000000");
+        loader.check(config.rate_limit_email_per_hour <= MAX_RATE && config.rate_limit_ip_per_hour <= MAX_RATE,
+            "RATE_LIMIT_EMAIL_PER_HOUR and RATE_LIMIT_IP_PER_HOUR must not exceed 1000000");
+
+        match loader.errors.is_empty() {
+            true => Ok(config),
+            false => Err(loader.errors)
         }
     }
 }
+
+// Settings without default, the tests add the ones they are about. The environment is ignored, the
+// database tests load .env into it
+#[cfg(test)]
+pub fn test_matches(args: &[&str]) -> ArgMatches {
+    let required = [
+        "server",
+        "--postgres-host", "localhost", "--postgres-user", "user", "--postgres-password", "password", "--postgres-db", "db",
+        "--jwt-secret", "secret", "--code-secret", "secret", "--front-url", "http://localhost:3000",
+        "--mail-transport", "memory", "--mail-from", "noreply@example.com",
+    ];
+    command()
+        .mut_args(|arg| arg.env(None))
+        .get_matches_from(required.iter().chain(args))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{test_matches, Config};
+    use crate::modules::openapi::ApiDocsUi;
+
+    #[test]
+    fn defaults_fill_the_optional_settings() {
+        let config = Config::load(&test_matches(&[])).unwrap();
+
+        assert_eq!(config.code_ttl_minutes, 5);
+        assert_eq!(config.backend_url, "http://127.0.0.1:8000");
+        assert_eq!(config.api_docs_ui, ApiDocsUi::None);
+        assert!(config.trusted_proxies.is_empty());
+    }
+
+    #[test]
+    fn invalid_settings_are_all_reported() {
+        let errors = Config::load(&test_matches(&[
+            "--backend-port", "port", "--code-ttl-minutes", "0", "--lockout-max-minutes", "9999999999",
+            "--jwt-expired-in", "0s", "--rate-limit-ip-per-hour", "inf",
+        ])).unwrap_err();
+
+        for expected in [
+            "BACKEND_PORT has an invalid value \"port\"",
+            "CODE_TTL_MINUTES must be between 1 and 1440",
+            "LOCKOUT_MAX_MINUTES must not exceed 525600",
+            "JWT_EXPIRED_IN must be positive",
+            "RATE_LIMIT_EMAIL_PER_HOUR and RATE_LIMIT_IP_PER_HOUR must not exceed 1000000",
+        ] {
+            assert!(errors.iter().any(|error| error.starts_with(expected)), "{} is not reported in {:?}", expected, errors);
+        }
+    }
+
+    #[test]
+    fn trusted_proxies_are_a_list_of_addresses() {
+        let config = Config::load(&test_matches(&["--trusted-proxies", "10.0.0.1, ::1"])).unwrap();
+        assert_eq!(config.trusted_proxies, ["10.0.0.1".parse::<IpAddr>().unwrap(), "::1".parse().unwrap()]);
+
+        let errors = Config::load(&test_matches(&["--trusted-proxies", "10.0.0.1,proxy"])).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("TRUSTED_PROXIES has an invalid value"));
+    }
+}
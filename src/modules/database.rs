@@ -1,26 +1,66 @@
+use std::{str::FromStr, time::Duration};
 use sqlx::{postgres::{PgPoolOptions, PgConnectOptions}, Postgres, Pool};
 use crate::config::Config;
 
+// Upper bound of the doubling delay between two connection attempts, unless the first delay is longer
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+pub fn connect_options(config: &Config) -> PgConnectOptions {
+    let mut options = match &config.database_url {
+        // Already validated with the configuration
+        Some(url) => PgConnectOptions::from_str(url.expose()).unwrap(),
+        None => PgConnectOptions::new()
+            .host(&config.postgres_host)
+            .port(config.postgres_port)
+            .username(&config.postgres_user)
+            .password(config.postgres_pwd.expose())
+            .database(&config.postgres_db)
+    };
+
+    if let Some(ssl_mode) = config.database_ssl_mode {
+        options = options.ssl_mode(ssl_mode);
+    }
+    if let Some(ssl_root_cert) = &config.database_ssl_root_cert {
+        options = options.ssl_root_cert(ssl_root_cert);
+    }
+    if !config.database_statement_timeout.is_zero() {
+        options = options.options([("statement_timeout", config.database_statement_timeout.as_millis().to_string())]);
+    }
+    options
+}
+
+fn pool_options(config: &Config) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(config.database_max_connections)
+        .min_connections(config.database_min_connections)
+        .acquire_timeout(config.database_acquire_timeout)
+        .idle_timeout((!config.database_idle_timeout.is_zero()).then_some(config.database_idle_timeout))
+}
+
+// Retries with a doubling delay so that the server can start before the database is ready
 pub async fn init(config: &Config) -> Pool<Postgres> {
-    let pool_options = PgConnectOptions::new()
-        .host(&config.postgres_host)
-        .port(config.postgres_port)
-        .username(&config.postgres_user)
-        .password(&config.postgres_pwd)
-        .database(&config.postgres_db);
+    let mut delay = config.database_connect_retry_delay;
+    let max_delay = MAX_RETRY_DELAY.max(delay);
 
-    match PgPoolOptions::new()
-        .max_connections(10)
-        .connect_with(pool_options)
-        .await
-    {
-        Ok(pool) => {
-            println!("✅Connection to the database is successful!");
-            pool
-        }
-        Err(err) => {
-            println!("🔥 Failed to connect to the database: {:?}", err);
-            std::process::exit(1);
+    if config.database_url.is_some() && !config.postgres_host.is_empty() {
+        println!("DATABASE_URL is set, the POSTGRES_ settings are ignored");
+    }
+
+    for attempt in 0..=config.database_connect_retries {
+        match pool_options(config).connect_with(connect_options(config)).await {
+            Ok(pool) => {
+                println!("✅Connection to the database is successful!");
+                return pool;
+            }
+            Err(err) if attempt < config.database_connect_retries => {
+                println!("🔥 Failed to connect to the database: {}, retrying in {:?} ({}/{})", err, delay, attempt + 1, config.database_connect_retries);
+                actix_web::rt::time::sleep(delay).await;
+                delay = delay.saturating_mul(2).min(max_delay);
+            }
+            Err(err) => {
+                println!("🔥 Failed to connect to the database: {}", err);
+            }
         }
     }
+    std::process::exit(1);
 }
\ No newline at end of file
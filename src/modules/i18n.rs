@@ -0,0 +1,78 @@
+use std::{
+    collections::HashMap,
+    fs };
+use actix_web::HttpRequest;
+
+use crate::shared::tools::get_accept_language;
+
+// Picks the preferred supported language of an Accept-Language header, a region falls back to its
+// primary language (fr-CA to fr)
+pub fn negotiate(accept_language: &str, supported: &[String]) -> Option<String> {
+    let mut ranges: Vec<(String, f32)> = accept_language.split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim().to_lowercase();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|value| value.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranges.iter().find_map(|(tag, _)| {
+        let primary = tag.split('-').next().unwrap_or(tag);
+        supported.iter()
+            .find(|code| code.as_str() == tag || code.as_str() == primary)
+            .cloned()
+    })
+}
+
+// API messages of each language, read from `<code>.json` files mapping message keys to texts
+#[derive(Debug, Clone)]
+pub struct I18n {
+    messages: HashMap<String, HashMap<String, String>>,
+    default_language: String,
+}
+
+impl I18n {
+    pub fn load(dir: &str, default_language: &str) -> I18n {
+        let mut messages = HashMap::new();
+        let entries = fs::read_dir(dir).unwrap_or_else(|_| panic!("Locales directory {} cannot be read", dir));
+
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let language = path.file_stem().unwrap().to_string_lossy().to_lowercase();
+            let content = fs::read_to_string(&path).unwrap_or_else(|_| panic!("Locale {} cannot be read", path.display()));
+            let catalog: HashMap<String, String> = serde_json::from_str(&content)
+                .unwrap_or_else(|e| panic!("Locale {} is invalid: {}", path.display(), e));
+            messages.insert(language, catalog);
+        }
+
+        if !messages.contains_key(default_language) {
+            panic!("Locale of the default language {} is missing in {}", default_language, dir);
+        }
+
+        I18n {
+            messages,
+            default_language: default_language.to_string(),
+        }
+    }
+
+    pub fn language(&self, req: &HttpRequest) -> String {
+        let supported: Vec<String> = self.messages.keys().cloned().collect();
+        get_accept_language(req)
+            .and_then(|header| negotiate(&header, &supported))
+            .unwrap_or_else(|| self.default_language.clone())
+    }
+
+    // Falls back to the default language
+    pub fn message(&self, language: &str, key: &str) -> Option<String> {
+        [language, self.default_language.as_str()].iter()
+            .find_map(|language| self.messages.get(*language).and_then(|catalog| catalog.get(key)))
+            .cloned()
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    fs };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    errors::{Error, ErrorKind},
+    jwk::{AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters,
+          OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType},
+    Algorithm, DecodingKey, EncodingKey, Header, Validation };
+use serde::{de::DeserializeOwned, Serialize};
+use simple_asn1::{oid, ASN1Block, BigInt};
+
+use crate::modules::config::Config;
+
+fn base64_integer(value: &BigInt) -> String {
+    URL_SAFE_NO_PAD.encode(value.to_bytes_be().1)
+}
+
+// Reads a SubjectPublicKeyInfo PEM (openssl pkey -pubout) holding an RSA or an Ed25519 key
+fn public_key_jwk(kid: &str, content: &[u8]) -> Result<Jwk, String> {
+    let pem = pem::parse(content).map_err(|e| e.to_string())?;
+    let blocks = simple_asn1::from_der(pem.contents()).map_err(|e| e.to_string())?;
+
+    let (identifier, key) = match blocks.as_slice() {
+        [ASN1Block::Sequence(_, info)] => match info.as_slice() {
+            [ASN1Block::Sequence(_, algorithm), ASN1Block::BitString(_, _, key)] => match algorithm.first() {
+                Some(ASN1Block::ObjectIdentifier(_, identifier)) => (identifier.clone(), key.clone()),
+                _ => return Err("missing key algorithm".to_string())
+            },
+            _ => return Err("expected a PUBLIC KEY".to_string())
+        },
+        _ => return Err("expected a PUBLIC KEY".to_string())
+    };
+
+    let (key_algorithm, algorithm) = if identifier == oid!(1, 2, 840, 113549, 1, 1, 1) {
+        let (n, e) = match simple_asn1::from_der(&key).map_err(|e| e.to_string())?.as_slice() {
+            [ASN1Block::Sequence(_, numbers)] => match numbers.as_slice() {
+                [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => (base64_integer(n), base64_integer(e)),
+                _ => return Err("invalid RSA key".to_string())
+            },
+            _ => return Err("invalid RSA key".to_string())
+        };
+        (KeyAlgorithm::RS256, AlgorithmParameters::RSA(RSAKeyParameters { key_type: RSAKeyType::RSA, n, e }))
+    }
+    else if identifier == oid!(1, 3, 101, 112) {
+        (KeyAlgorithm::EdDSA, AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(key),
+        }))
+    }
+    else {
+        return Err("only RSA and Ed25519 keys are supported".to_string());
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm,
+    })
+}
+
+// Signs the access and refresh tokens with the current key and verifies them with any key still
+// listed, so that a new key can be rolled out before the previous one is removed. HS256 keeps the
+// shared secret and publishes no key
+#[derive(Clone)]
+pub struct JwtKeys {
+    kid: String,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: HashMap<String, (Algorithm, DecodingKey)>,
+    jwks: JwkSet,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &Config) -> JwtKeys {
+        let kid = config.jwt_key_id.clone();
+
+        if config.jwt_algorithm == Algorithm::HS256 {
+            return JwtKeys {
+                kid: kid.clone(),
+                algorithm: Algorithm::HS256,
+                encoding: EncodingKey::from_secret(config.jwt_secret.expose().as_bytes()),
+                decoding: HashMap::from([(kid, (Algorithm::HS256, DecodingKey::from_secret(config.jwt_secret.expose().as_bytes())))]),
+                jwks: JwkSet { keys: vec![] },
+            };
+        }
+
+        let private_key = fs::read(&config.jwt_private_key_file)
+            .unwrap_or_else(|_| panic!("JWT private key {} cannot be read", config.jwt_private_key_file));
+        let encoding = match config.jwt_algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_key),
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(&private_key),
+            algorithm => panic!("Unsupported JWT algorithm {:?}, expected HS256, RS256 or EdDSA", algorithm)
+        }.unwrap_or_else(|e| panic!("JWT private key {} is invalid: {}", config.jwt_private_key_file, e));
+
+        let mut keys = vec![];
+        let entries = fs::read_dir(&config.jwt_public_keys_dir)
+            .unwrap_or_else(|_| panic!("JWT public keys directory {} cannot be read", config.jwt_public_keys_dir));
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_none_or(|extension| extension != "pem") {
+                continue;
+            }
+            let key_id = path.file_stem().unwrap().to_string_lossy().to_string();
+            let content = fs::read(&path).unwrap_or_else(|_| panic!("JWT public key {} cannot be read", path.display()));
+            keys.push(public_key_jwk(&key_id, &content)
+                .unwrap_or_else(|e| panic!("JWT public key {} is invalid: {}", path.display(), e)));
+        }
+
+        let decoding: HashMap<String, (Algorithm, DecodingKey)> = keys.iter()
+            .map(|jwk| {
+                let algorithm = match jwk.algorithm {
+                    AlgorithmParameters::RSA(_) => Algorithm::RS256,
+                    _ => Algorithm::EdDSA,
+                };
+                (jwk.common.key_id.clone().unwrap(), (algorithm, DecodingKey::from_jwk(jwk).unwrap()))
+            })
+            .collect();
+
+        match decoding.get(&kid) {
+            Some((algorithm, _)) if *algorithm == config.jwt_algorithm => (),
+            _ => panic!("JWT public key {}.pem of the {:?} signing key is missing in {}", kid, config.jwt_algorithm, config.jwt_public_keys_dir)
+        }
+
+        JwtKeys {
+            kid,
+            algorithm: config.jwt_algorithm,
+            encoding,
+            decoding,
+            jwks: JwkSet { keys },
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> String {
+        let header = Header {
+            kid: Some(self.kid.clone()),
+            ..Header::new(self.algorithm)
+        };
+        jsonwebtoken::encode(&header, claims, &self.encoding).unwrap()
+    }
+
+    // Tokens without kid were issued before key rotation, they are checked with the current key
+    pub fn decode<T: DeserializeOwned>(&self, value: &str) -> Result<T, Error> {
+        let kid = jsonwebtoken::decode_header(value)?.kid.unwrap_or_else(|| self.kid.clone());
+        let (algorithm, key) = self.decoding.get(&kid).ok_or(Error::from(ErrorKind::InvalidKeyFormat))?;
+
+        jsonwebtoken::decode::<T>(value, key, &Validation::new(*algorithm))
+            .map(|data| data.claims)
+    }
+
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
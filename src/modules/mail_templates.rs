@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path };
+
+// One plain-text template `<kind>.txt` starting with a `Subject:` line and one `<kind>.html` template
+// per kind, in a directory per language code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MailKind {
+    RegisterCode,
+    LoginCode,
+    ResendCode,
+    RetryCode,
+    MagicLink,
+    AccountLocked,
+}
+
+impl MailKind {
+    pub const ALL: [MailKind; 6] = [
+        MailKind::RegisterCode,
+        MailKind::LoginCode,
+        MailKind::ResendCode,
+        MailKind::RetryCode,
+        MailKind::MagicLink,
+        MailKind::AccountLocked,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MailKind::RegisterCode => "register_code",
+            MailKind::LoginCode => "login_code",
+            MailKind::ResendCode => "resend_code",
+            MailKind::RetryCode => "retry_code",
+            MailKind::MagicLink => "magic_link",
+            MailKind::AccountLocked => "account_locked",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MailTemplate {
+    subject: String,
+    text: String,
+    html: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedMail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailTemplates {
+    templates: HashMap<(String, MailKind), MailTemplate>,
+    default_language: String,
+}
+
+fn read_template(dir: &Path, kind: MailKind) -> Option<MailTemplate> {
+    let text = fs::read_to_string(dir.join(format!("{}.txt", kind.name()))).ok()?;
+    let html = fs::read_to_string(dir.join(format!("{}.html", kind.name()))).ok()?;
+    let (subject, text) = text.strip_prefix("Subject:")?.split_once('\n')?;
+
+    Some(MailTemplate {
+        subject: subject.trim().to_string(),
+        text: text.trim().to_string(),
+        html,
+    })
+}
+
+fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Replaces every `{{name}}` with its value, unknown names are left untouched
+fn render(template: &str, vars: &[(&str, String)], escape: bool) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else { break };
+        let placeholder = &rest[start..start + end + 2];
+        let name = placeholder[2..placeholder.len() - 2].trim();
+
+        match vars.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) if escape => output.push_str(&escape_html(value)),
+            Some((_, value)) => output.push_str(value),
+            None => output.push_str(placeholder),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+impl MailTemplates {
+    // Every kind must exist in the default language, the other languages may be partial
+    pub fn load(dir: &str, default_language: &str) -> MailTemplates {
+        let mut templates = HashMap::new();
+        let entries = fs::read_dir(dir).unwrap_or_else(|_| panic!("Mail templates directory {} cannot be read", dir));
+
+        for entry in entries.flatten().filter(|entry| entry.path().is_dir()) {
+            let language = entry.file_name().to_string_lossy().to_lowercase();
+            for kind in MailKind::ALL {
+                if let Some(template) = read_template(&entry.path(), kind) {
+                    templates.insert((language.clone(), kind), template);
+                }
+            }
+        }
+
+        for kind in MailKind::ALL {
+            if !templates.contains_key(&(default_language.to_string(), kind)) {
+                panic!("Mail template {} is missing for the default language {}", kind.name(), default_language);
+            }
+        }
+
+        MailTemplates {
+            templates,
+            default_language: default_language.to_string(),
+        }
+    }
+
+    pub fn render(&self, kind: MailKind, language: &str, vars: &[(&str, String)]) -> RenderedMail {
+        let template = self.templates.get(&(language.to_lowercase(), kind))
+            .unwrap_or_else(|| &self.templates[&(self.default_language.clone(), kind)]);
+
+        RenderedMail {
+            subject: render(&template.subject, vars, false),
+            text: render(&template.text, vars, false),
+            html: render(&template.html, vars, true),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    error::Error,
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex} };
+use async_trait::async_trait;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use crate::config::Config;
+
+pub type MailError = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MailTransportKind {
+    #[default]
+    Smtp,
+    Plain,
+    File,
+    Stdout,
+    Memory,
+}
+
+impl FromStr for MailTransportKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "smtp" => Ok(MailTransportKind::Smtp),
+            "plain" => Ok(MailTransportKind::Plain),
+            "file" => Ok(MailTransportKind::File),
+            "stdout" => Ok(MailTransportKind::Stdout),
+            "memory" => Ok(MailTransportKind::Memory),
+            _ => Err(format!("Unknown mail transport {}, expected smtp, plain, file, stdout or memory", value))
+        }
+    }
+}
+
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, email: Message) -> Result<(), MailError>;
+}
+
+// Used for both the STARTTLS relay and the plain connection to local catchers such as MailHog
+#[async_trait]
+impl MailTransport for AsyncSmtpTransport<Tokio1Executor> {
+    async fn send(&self, email: Message) -> Result<(), MailError> {
+        AsyncTransport::send(self, email)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.into())
+    }
+}
+
+// Writes every message as an .eml file in a directory
+pub struct FileTransport {
+    dir: PathBuf,
+}
+
+impl FileTransport {
+    pub fn new(dir: PathBuf) -> FileTransport {
+        fs::create_dir_all(&dir).expect("MAIL_FILE_DIR cannot be created");
+        FileTransport { dir }
+    }
+}
+
+#[async_trait]
+impl MailTransport for FileTransport {
+    async fn send(&self, email: Message) -> Result<(), MailError> {
+        let path = self.dir.join(format!("{}.eml", uuid::Uuid::new_v4()));
+        fs::write(&path, email.formatted())?;
+        println!("📧 Email written to {}", path.display());
+        Ok(())
+    }
+}
+
+pub struct StdoutTransport;
+
+#[async_trait]
+impl MailTransport for StdoutTransport {
+    async fn send(&self, email: Message) -> Result<(), MailError> {
+        println!("📧 Email:\n{}", String::from_utf8_lossy(&email.formatted()));
+        Ok(())
+    }
+}
+
+// Keeps the messages so that they can be inspected, clones share the same messages
+#[derive(Clone, Default)]
+pub struct MemoryTransport {
+    messages: Arc<Mutex<Vec<Message>>>,
+}
+
+impl MemoryTransport {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn messages(&self) -> Vec<Message> {
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl MailTransport for MemoryTransport {
+    async fn send(&self, email: Message) -> Result<(), MailError> {
+        self.messages.lock().unwrap().push(email);
+        Ok(())
+    }
+}
+
+pub fn from_config(config: &Config) -> Arc<dyn MailTransport> {
+    match config.mail_transport {
+        MailTransportKind::Smtp => Arc::new(AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.mail_host)
+            .expect("MAIL_HOST is not a valid host")
+            .port(config.mail_port)
+            .credentials(Credentials::new(config.mail_auth_user.to_string(), config.mail_auth_pwd.expose().to_string()))
+            .build()),
+        MailTransportKind::Plain => {
+            let builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.mail_host)
+                .port(config.mail_port);
+            // Local catchers usually accept any message without authentication
+            if config.mail_auth_pwd.is_empty() {
+                Arc::new(builder.build())
+            }
+            else {
+                Arc::new(builder
+                    .credentials(Credentials::new(config.mail_auth_user.to_string(), config.mail_auth_pwd.expose().to_string()))
+                    .build())
+            }
+        },
+        MailTransportKind::File => Arc::new(FileTransport::new(PathBuf::from(&config.mail_file_dir))),
+        MailTransportKind::Stdout => Arc::new(StdoutTransport),
+        MailTransportKind::Memory => Arc::new(MemoryTransport::default()),
+    }
+}
@@ -1,48 +1,151 @@
-use lettre::transport::smtp::authentication::Credentials; 
-use lettre::{Message, SmtpTransport, Transport, Address}; 
-use lettre::message::Mailbox;
+use std::{sync::Arc, time::Duration};
+use chrono::Utc;
+use lettre::{Message, Address}; 
+use lettre::message::{Mailbox, MultiPart};
+use sqlx::{Postgres, Pool, PgExecutor};
 use crate::config::Config;
+use crate::models::OutboxMessage;
+use crate::modules::mail_transport::{self, MailError, MailTransport};
+use crate::modules::mail_templates::{MailKind, MailTemplates};
 
+const BATCH_SIZE: i64 = 20;
+
+// Handlers only queue messages in the outbox, a background task delivers them
 #[derive(Clone)]
 pub struct Mailer {
-    host: String,
-    port: u16,
-    auth_user: String,
-    auth_pwd: String,
+    from: Mailbox,
     app_name: String,
+    templates: Arc<MailTemplates>,
+    transport: Arc<dyn MailTransport>,
+    max_attempts: i16,
+    retry_base_seconds: i64,
+    poll_interval: Duration,
+    code_ttl_minutes: i64,
 }
 
 impl Mailer {
     pub fn new(config: &Config) -> Mailer {
+        Mailer::with_transport(config, mail_transport::from_config(config))
+    }
+
+    pub fn with_transport(config: &Config, transport: Arc<dyn MailTransport>) -> Mailer {
         Mailer {
-            host: config.mail_host.clone(),
-            port: config.mail_port.clone(),
-            auth_user: config.mail_auth_user.clone(),
-            auth_pwd: config.mail_auth_pwd.clone(),
-            app_name: config.app_name.clone()
+            // Already validated with the configuration
+            from: Mailbox::new(Some(config.app_name.clone()), config.mail_from.parse::<Address>().unwrap()),
+            app_name: config.app_name.clone(),
+            templates: Arc::new(MailTemplates::load(&config.mail_templates_dir, &config.default_language)),
+            transport,
+            max_attempts: config.mail_max_attempts,
+            retry_base_seconds: config.mail_retry_base_seconds,
+            poll_interval: Duration::from_secs(config.mail_poll_seconds),
+            code_ttl_minutes: config.code_ttl_minutes,
         }
     }
 
-    pub fn send_message(&self, receiver: String, subject: String, message: String) {
-      let email = Message::builder() 
-        .from(Mailbox::new(Some(self.app_name.clone()), self.auth_user.parse::<Address>().unwrap())) 
-        .to(Mailbox::new(None, receiver.parse().unwrap())) 
-        .subject(&subject) 
-        .message_id(None)
-        .body(message) 
-        .unwrap(); 
-
-      let creds = Credentials::new(self.auth_user.to_string(), self.auth_pwd.to_string()); 
-      let mailer = SmtpTransport::starttls_relay(&self.host)
-        .unwrap() 
-        .port(self.port)
-        .credentials(creds) 
-        .build();
-
-      match mailer.send(&email) { 
-        Ok(_) => println!("Email sent successfully!"), 
-        Err(e) => panic!("Could not send email: {:?}", e), 
-      }
-    }
-   
+    // Renders the template in the user language, pass a transaction to only send the message if the rest
+    // of the request is committed
+    pub async fn queue_mail<'e, E: PgExecutor<'e>>(&self, receiver: String, kind: MailKind, language: &str, vars: &[(&str, String)], db: E) -> Result<(), sqlx::Error> {
+        let vars = [vars, &[("app_name", self.app_name.clone())]].concat();
+        let mail = self.templates.render(kind, language, &vars);
+
+        OutboxMessage::enqueue(receiver, mail.subject, mail.text, Some(mail.html), db)
+            .await
+            .map(|_| ())
+    }
+
+    pub fn start_sender(&self, db: Pool<Postgres>) {
+        let mailer = self.clone();
+        actix_web::rt::spawn(async move {
+            loop {
+                if let Err(e) = OutboxMessage::purge_stale(mailer.code_ttl_minutes, &db).await {
+                    println!("🔥 Could not purge the mail outbox: {:?}", e);
+                }
+                match OutboxMessage::claim_due(BATCH_SIZE, &db).await {
+                    Ok(messages) => for message in messages {
+                        mailer.deliver(message, &db).await;
+                    },
+                    Err(e) => println!("🔥 Could not read the mail outbox: {:?}", e)
+                }
+                actix_web::rt::time::sleep(mailer.poll_interval).await;
+            }
+        });
+    }
+
+    // Sends right away without going through the outbox, to check the transport settings
+    pub async fn send_test(&self, receiver: &str) -> Result<(), MailError> {
+        let subject = format!("{} test email", self.app_name);
+        let body = format!("This email was sent by {} to check the mail settings.", self.app_name);
+        let email = self.build(receiver, &subject, &body, None)?;
+        self.transport.send(email).await
+    }
+
+    fn build(&self, recipient: &str, subject: &str, body: &str, html_body: Option<&str>) -> Result<Message, String> {
+        let address = recipient.parse::<Address>().map_err(|e| e.to_string())?;
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(Mailbox::new(None, address))
+            .subject(subject)
+            .message_id(None);
+        let email = match html_body {
+            Some(html_body) => builder.multipart(MultiPart::alternative_plain_html(body.to_string(), html_body.to_string())),
+            None => builder.body(body.to_string())
+        };
+        email.map_err(|e| e.to_string())
+    }
+
+    async fn deliver(&self, message: OutboxMessage, db: &Pool<Postgres>) {
+        let email = self.build(&message.recipient, &message.subject, &message.body, message.html_body.as_deref());
+
+        let update = match email {
+            Ok(email) => match self.transport.send(email).await {
+                Ok(_) => {
+                    println!("Email {} sent successfully!", message.id);
+                    OutboxMessage::mark_sent(message.id, db).await
+                },
+                // Delivery errors are retried with an exponential backoff
+                Err(e) if message.attempts + 1 < self.max_attempts => {
+                    let delay = self.retry_base_seconds.saturating_mul(1i64 << message.attempts.clamp(0, 30));
+                    println!("Could not send email {}, retrying in {}s: {:?}", message.id, delay, e);
+                    OutboxMessage::retry_at(message.id, &e.to_string(), Utc::now() + chrono::Duration::seconds(delay), db).await
+                },
+                Err(e) => {
+                    println!("Could not send email {}, giving up: {:?}", message.id, e);
+                    OutboxMessage::mark_failed(message.id, &e.to_string(), db).await
+                }
+            },
+            // A message that cannot be built will never be sent
+            Err(e) => {
+                println!("Could not build email {}: {}", message.id, e);
+                OutboxMessage::mark_failed(message.id, &e, db).await
+            }
+        };
+
+        if let Err(e) = update {
+            println!("🔥 Could not update the mail outbox: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Mailer;
+    use crate::modules::config::{test_matches, Config};
+    use crate::modules::mail_transport::MemoryTransport;
+
+    #[actix_web::test]
+    async fn send_test_goes_through_the_transport() {
+        let config = Config::load(&test_matches(&["--app-name", "Test"])).unwrap();
+        let transport = MemoryTransport::default();
+
+        Mailer::with_transport(&config, Arc::new(transport.clone())).send_test("user@example.com").await.unwrap();
+
+        let messages = transport.messages();
+        assert_eq!(messages.len(), 1);
+        let headers = messages[0].headers();
+        assert_eq!(headers.get_raw("From"), Some("Test <noreply@example.com>"));
+        assert_eq!(headers.get_raw("To"), Some("user@example.com"));
+        assert_eq!(headers.get_raw("Subject"), Some("Test test email"));
+    }
 }
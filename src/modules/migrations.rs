@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use clap::ArgMatches;
+use sqlx::{migrate::{Migrate, MigrateError, Migrator}, Postgres, Pool};
+
+// Migrations of the migrations directory, embedded in the binary at compile time
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub async fn status(db: &Pool<Postgres>) -> Result<(), MigrateError> {
+    let mut conn = db.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied: HashMap<i64, Vec<u8>> = conn.list_applied_migrations().await?
+        .into_iter()
+        .map(|migration| (migration.version, migration.checksum.into_owned()))
+        .collect();
+
+    for migration in MIGRATOR.iter().filter(|migration| migration.migration_type.is_up_migration()) {
+        let state = match applied.get(&migration.version) {
+            None => "pending",
+            Some(checksum) if *checksum != *migration.checksum => "applied, modified since",
+            Some(_) => "applied",
+        };
+        println!("{} {:<24} {}", migration.version, migration.description, state);
+    }
+    Ok(())
+}
+
+pub async fn up(db: &Pool<Postgres>) -> Result<(), MigrateError> {
+    MIGRATOR.run(db).await?;
+    println!("✅ Database migrations are up to date");
+    Ok(())
+}
+
+// Reverts the latest applied migration only
+pub async fn down(db: &Pool<Postgres>) -> Result<(), MigrateError> {
+    let mut conn = db.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let mut versions: Vec<i64> = conn.list_applied_migrations().await?
+        .iter()
+        .map(|migration| migration.version)
+        .collect();
+    drop(conn);
+    versions.sort_unstable();
+
+    match versions.last() {
+        None => println!("No migration to revert"),
+        Some(version) => {
+            let target = versions.iter().rev().nth(1).copied().unwrap_or(0);
+            MIGRATOR.undo(db, target).await?;
+            println!("✅ Reverted the migration {}", version);
+        }
+    }
+    Ok(())
+}
+
+// Runs a subcommand of `migrate`
+pub async fn run(command: &ArgMatches, db: &Pool<Postgres>) -> Result<(), MigrateError> {
+    match command.subcommand_name() {
+        Some("status") => status(db).await,
+        Some("up") => up(db).await,
+        Some("down") => down(db).await,
+        _ => unreachable!("the migrate subcommand is required")
+    }
+}
@@ -1,3 +1,11 @@
 pub mod config;
 pub mod database;
+pub mod migrations;
+pub mod cli;
 pub mod mailer;
+pub mod mail_transport;
+pub mod mail_templates;
+pub mod i18n;
+pub mod rate_limiter;
+pub mod openapi;
+pub mod jwt_keys;
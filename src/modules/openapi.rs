@@ -0,0 +1,151 @@
+use std::str::FromStr;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi };
+
+use crate::api_schemas::{RegisterRequestSchema, ConfirmCodeRequestSchema, LoginRequestSchema, LoginMode, RefreshRequestSchema, MagicLinkRequestSchema, UpdateRoleRequestSchema};
+use crate::models::{User, Language};
+use crate::response::{
+    StatusResponse, MessageResponse, ErrorResponse, FieldError, TokensResponse, TokensData, UserResponse, UserData,
+    SessionsResponse, SessionsData, SessionInfo, LanguagesResponse, LanguagesData, UsersResponse, UsersData, RoleResponse };
+use crate::services::{authentication, health_checker, languages, account, admin, docs, well_known};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ApiDocsUi {
+    #[default]
+    None,
+    Swagger,
+    Redoc,
+}
+
+impl FromStr for ApiDocsUi {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "none" => Ok(ApiDocsUi::None),
+            "swagger" => Ok(ApiDocsUi::Swagger),
+            "redoc" => Ok(ApiDocsUi::Redoc),
+            _ => Err(format!("Unknown API docs UI {}, expected none, swagger or redoc", value))
+        }
+    }
+}
+
+// Exact releases, a new version of the UI is taken on purpose and not at the next page load
+const SWAGGER_UI_CSS: &str = "https://unpkg.com/swagger-ui-dist@5.11.0/swagger-ui.css";
+const SWAGGER_UI_JS: &str = "https://unpkg.com/swagger-ui-dist@5.11.0/swagger-ui-bundle.js";
+const REDOC_JS: &str = "https://cdn.redoc.ly/redoc/v2.1.3/bundles/redoc.standalone.js";
+
+fn swagger_init(spec_url: &str) -> String {
+    format!(r##"window.ui = SwaggerUIBundle({{ url: "{}", dom_id: "#swagger-ui" }});"##, spec_url)
+}
+
+impl ApiDocsUi {
+    // Page loading the UI from its CDN and pointing it at the document
+    pub fn page(&self, spec_url: &str) -> Option<String> {
+        match self {
+            ApiDocsUi::None => None,
+            ApiDocsUi::Swagger => Some(format!(r##"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>API documentation</title>
+    <link rel="stylesheet" href="{}">
+  </head>
+  <body>
+    <div id="swagger-ui"></div>
+    <script src="{}"></script>
+    <script>{}</script>
+  </body>
+</html>"##, SWAGGER_UI_CSS, SWAGGER_UI_JS, swagger_init(spec_url))),
+            ApiDocsUi::Redoc => Some(format!(r##"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>API documentation</title>
+  </head>
+  <body>
+    <redoc spec-url="{}"></redoc>
+    <script src="{}"></script>
+  </body>
+</html>"##, spec_url, REDOC_JS)),
+        }
+    }
+
+    // Only the pinned files and the inline script of the page may run, the UIs style their elements
+    // inline and Redoc renders in a blob worker
+    pub fn content_security_policy(&self, spec_url: &str) -> String {
+        let scripts = match self {
+            ApiDocsUi::Swagger => format!("{} 'sha256-{}'", SWAGGER_UI_JS, STANDARD.encode(Sha256::digest(swagger_init(spec_url)))),
+            _ => REDOC_JS.to_string(),
+        };
+        format!("default-src 'none'; script-src {}; style-src {} 'unsafe-inline'; img-src 'self' data:; font-src data:; \
+            connect-src 'self'; worker-src blob:; base-uri 'none'; form-action 'none'; frame-ancestors 'none'", scripts, SWAGGER_UI_CSS)
+    }
+}
+
+// The access token is accepted as a bearer token or in the access cookie
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(
+            HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()));
+        components.add_security_scheme("cookie", SecurityScheme::ApiKey(
+            ApiKey::Cookie(ApiKeyValue::new("access_cookie"))));
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        health_checker::health_checker_handler,
+        well_known::jwks_handler,
+        authentication::register_handler,
+        authentication::resend_code_handler,
+        authentication::confirm_code_handler,
+        authentication::login_handler,
+        authentication::magic_link_handler,
+        authentication::magic_link_confirm_handler,
+        authentication::unlock_handler,
+        authentication::refresh_handler,
+        authentication::logout_handler,
+        authentication::logout_all_handler,
+        languages::get_languages_handler,
+        account::check_handler,
+        account::get_me_handler,
+        account::get_sessions_handler,
+        account::revoke_session_handler,
+        admin::check_admin_handler,
+        admin::get_users_handler,
+        admin::get_user_handler,
+        admin::set_role_handler,
+        admin::verify_handler,
+        admin::disable_handler,
+        admin::enable_handler,
+        admin::revoke_sessions_handler,
+        admin::unlock_user_handler,
+        admin::delete_user_handler,
+        docs::openapi_handler,
+        docs::docs_handler,
+    ),
+    components(schemas(
+        RegisterRequestSchema, ConfirmCodeRequestSchema, LoginRequestSchema, LoginMode, RefreshRequestSchema, MagicLinkRequestSchema,
+        StatusResponse, MessageResponse, ErrorResponse, FieldError, TokensResponse, TokensData, UserResponse, UserData,
+        SessionsResponse, SessionsData, SessionInfo, LanguagesResponse, LanguagesData, User, Language,
+        UpdateRoleRequestSchema, UsersResponse, UsersData, RoleResponse,
+    )),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "service", description = "Service monitoring"),
+        (name = "authentication", description = "Passwordless registration, login and sessions"),
+        (name = "languages", description = "Supported languages"),
+        (name = "account", description = "Account of the authenticated user"),
+        (name = "admin", description = "Management of the users, requires the admin role"),
+        (name = "docs", description = "This document and its UI"),
+    )
+)]
+pub struct ApiDoc;
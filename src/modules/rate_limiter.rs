@@ -0,0 +1,213 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant} };
+use sqlx::{Postgres, Pool};
+use crate::config::Config;
+
+// Buckets that are full again are forgotten, which does not change their behaviour
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RateLimitStoreKind {
+    #[default]
+    Memory,
+    Postgres,
+}
+
+impl FromStr for RateLimitStoreKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "memory" => Ok(RateLimitStoreKind::Memory),
+            "postgres" => Ok(RateLimitStoreKind::Postgres),
+            _ => Err(format!("Unknown rate limit store {}, expected memory or postgres", value))
+        }
+    }
+}
+
+// Token bucket holding up to `capacity` requests and refilled with `per_hour` requests every hour
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitRule {
+    pub capacity: f64,
+    pub per_hour: f64,
+}
+
+impl RateLimitRule {
+    fn refill_per_second(&self) -> f64 {
+        self.per_hour / 3600.0
+    }
+
+    fn retry_after(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64(((1.0 - tokens) / self.refill_per_second()).max(1.0))
+    }
+
+    // Time for an empty bucket to be full again
+    fn refill_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.capacity / self.refill_per_second())
+    }
+}
+
+// Each bucket keeps the rule it was created with, since ip: and email: buckets share the store
+struct Bucket {
+    rule: RateLimitRule,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn tokens_at(&self, now: Instant) -> f64 {
+        (self.tokens + now.duration_since(self.updated_at).as_secs_f64() * self.rule.refill_per_second()).min(self.rule.capacity)
+    }
+}
+
+#[derive(Clone)]
+enum Store {
+    Memory(Arc<Mutex<HashMap<String, Bucket>>>),
+    Postgres(Pool<Postgres>),
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: Store,
+    pub email_rule: RateLimitRule,
+    pub ip_rule: RateLimitRule,
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config, db: &Pool<Postgres>) -> RateLimiter {
+        RateLimiter {
+            store: match config.rate_limit_store {
+                RateLimitStoreKind::Memory => Store::Memory(Arc::new(Mutex::new(HashMap::new()))),
+                RateLimitStoreKind::Postgres => Store::Postgres(db.clone()),
+            },
+            email_rule: RateLimitRule { capacity: config.rate_limit_email_capacity, per_hour: config.rate_limit_email_per_hour },
+            ip_rule: RateLimitRule { capacity: config.rate_limit_ip_capacity, per_hour: config.rate_limit_ip_per_hour },
+        }
+    }
+
+    // Forgets the full buckets every CLEANUP_INTERVAL
+    pub fn start_cleanup(&self) {
+        let rate_limiter = self.clone();
+        actix_web::rt::spawn(async move {
+            loop {
+                actix_web::rt::time::sleep(CLEANUP_INTERVAL).await;
+                match &rate_limiter.store {
+                    Store::Memory(buckets) => {
+                        let now = Instant::now();
+                        buckets.lock().unwrap().retain(|_, bucket| bucket.tokens_at(now) < bucket.rule.capacity);
+                    },
+                    Store::Postgres(db) => if let Err(e) = rate_limiter.purge_postgres(db).await {
+                        println!("🔥 Could not purge the rate limits: {:?}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    // Deletes the buckets of the rate_limits table that are full again, returns how many were deleted
+    pub async fn purge_postgres(&self, db: &Pool<Postgres>) -> Result<u64, sqlx::Error> {
+        let idle = self.email_rule.refill_duration().max(self.ip_rule.refill_duration());
+        sqlx::query!("DELETE FROM rate_limits WHERE updated_at < now() - make_interval(secs => $1)", idle.as_secs_f64())
+            .execute(db)
+            .await
+            .map(|res| res.rows_affected())
+    }
+
+    // Takes one token from the bucket of `key`, returns how long to wait when it is empty
+    pub async fn check(&self, key: &str, rule: &RateLimitRule) -> Result<Result<(), Duration>, sqlx::Error> {
+        match &self.store {
+            Store::Memory(buckets) => Ok(Self::check_memory(buckets, key, rule)),
+            Store::Postgres(db) => Self::check_postgres(db, key, rule).await,
+        }
+    }
+
+    fn check_memory(buckets: &Mutex<HashMap<String, Bucket>>, key: &str, rule: &RateLimitRule) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = buckets.lock().unwrap();
+
+        let bucket = buckets.entry(key.to_string())
+            .or_insert(Bucket { rule: *rule, tokens: rule.capacity, updated_at: now });
+        bucket.tokens = bucket.tokens_at(now);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(bucket.rule.retry_after(bucket.tokens))
+        }
+    }
+
+    async fn check_postgres(db: &Pool<Postgres>, key: &str, rule: &RateLimitRule) -> Result<Result<(), Duration>, sqlx::Error> {
+        let row = sqlx::query!(
+            "INSERT INTO rate_limits AS r (key, tokens, last_allowed) VALUES ($1, $2::DOUBLE PRECISION - 1, TRUE)
+             ON CONFLICT (key) DO UPDATE SET
+                tokens = LEAST($2, r.tokens + EXTRACT(EPOCH FROM now() - r.updated_at)::DOUBLE PRECISION * $3)
+                    - CASE WHEN LEAST($2, r.tokens + EXTRACT(EPOCH FROM now() - r.updated_at)::DOUBLE PRECISION * $3) >= 1 THEN 1 ELSE 0 END,
+                last_allowed = LEAST($2, r.tokens + EXTRACT(EPOCH FROM now() - r.updated_at)::DOUBLE PRECISION * $3) >= 1,
+                updated_at = now()
+             RETURNING tokens, last_allowed",
+            key,
+            rule.capacity,
+            rule.refill_per_second(),
+        )
+            .fetch_one(db)
+            .await?;
+
+        if row.last_allowed {
+            Ok(Ok(()))
+        } else {
+            Ok(Err(rule.retry_after(row.tokens)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Mutex, time::{Duration, Instant}};
+
+    use super::{Bucket, RateLimitRule, RateLimiter};
+
+    // One token per second
+    const RULE: RateLimitRule = RateLimitRule { capacity: 3.0, per_hour: 3600.0 };
+
+    #[test]
+    fn allows_the_capacity_then_asks_to_wait() {
+        let buckets = Mutex::new(HashMap::new());
+
+        for _ in 0..3 {
+            assert_eq!(RateLimiter::check_memory(&buckets, "ip:10.0.0.1", &RULE), Ok(()));
+        }
+        let retry_after = RateLimiter::check_memory(&buckets, "ip:10.0.0.1", &RULE).unwrap_err();
+        assert!(retry_after >= Duration::from_secs(1) && retry_after <= Duration::from_secs(2));
+
+        assert_eq!(RateLimiter::check_memory(&buckets, "ip:10.0.0.2", &RULE), Ok(()));
+    }
+
+    #[test]
+    fn buckets_refill_over_time_up_to_their_capacity() {
+        let now = Instant::now();
+        let bucket = Bucket { rule: RULE, tokens: 0.0, updated_at: now - Duration::from_secs(2) };
+        assert!((bucket.tokens_at(now) - 2.0).abs() < 0.01);
+
+        let bucket = Bucket { rule: RULE, tokens: 0.0, updated_at: now - Duration::from_secs(60) };
+        assert_eq!(bucket.tokens_at(now), 3.0);
+    }
+
+    #[test]
+    fn buckets_keep_the_rule_they_were_created_with() {
+        let buckets = Mutex::new(HashMap::new());
+        let strict = RateLimitRule { capacity: 1.0, per_hour: 1.0 };
+
+        assert_eq!(RateLimiter::check_memory(&buckets, "email:a@example.com", &strict), Ok(()));
+        assert!(RateLimiter::check_memory(&buckets, "email:a@example.com", &RULE).is_err());
+    }
+
+    #[test]
+    fn refill_duration_is_the_time_to_fill_an_empty_bucket() {
+        assert_eq!(RULE.refill_duration(), Duration::from_secs(3));
+    }
+}
@@ -1,24 +1,197 @@
+// Bodies of the API responses, the handlers serialize them and the OpenAPI document describes them
+
+use std::collections::BTreeMap;
 use chrono::prelude::*;
 use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::models::{User, Language};
+
+const SUCCESS: &str = "success";
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatusResponse {
+    #[schema(example = "success")]
+    pub status: String,
+}
+
+impl StatusResponse {
+    pub fn success() -> StatusResponse {
+        StatusResponse { status: SUCCESS.to_string() }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub message: String,
+}
+
+impl MessageResponse {
+    pub fn success(message: &str) -> MessageResponse {
+        MessageResponse { status: SUCCESS.to_string(), message: message.to_string() }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    #[schema(example = "invalid_email")]
+    pub code: String,
+    pub message: String,
+}
 
 #[allow(non_snake_case)]
-#[derive(Debug, Serialize)]
-pub struct FilteredUser {
-    pub id: String,
-    pub email: String,
-    pub role: String,
-    pub verified: bool,
-    pub createdAt: DateTime<Utc>,
-    pub updatedAt: DateTime<Utc>,
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    // fail for client errors, error for server ones
+    #[schema(example = "fail")]
+    pub status: String,
+    #[schema(example = "not_logged_in")]
+    pub code: String,
+    pub message: String,
+    // Parser error of an invalid_json body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    // Errors of each field of an invalid_request body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<BTreeMap<String, Vec<FieldError>>>,
+    // End of the cooldown of an account_locked error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockedUntil: Option<DateTime<Utc>>,
+    // A new code has been sent after an invalid_email_or_code error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "true")]
+    pub newCode: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokensData {
+    #[schema(example = "Bearer")]
+    pub tokenType: String,
+    pub accessToken: String,
+    pub refreshToken: String,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokensResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub data: TokensData,
+}
+
+impl TokensResponse {
+    pub fn bearer(access_token: String, refresh_token: String) -> TokensResponse {
+        TokensResponse {
+            status: SUCCESS.to_string(),
+            data: TokensData { tokenType: "Bearer".to_string(), accessToken: access_token, refreshToken: refresh_token },
+        }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
 pub struct UserData {
-    pub user: FilteredUser,
+    pub user: User,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, ToSchema)]
 pub struct UserResponse {
+    #[schema(example = "success")]
     pub status: String,
     pub data: UserData,
 }
+
+impl UserResponse {
+    pub fn new(user: User) -> UserResponse {
+        UserResponse { status: SUCCESS.to_string(), data: UserData { user } }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct UsersData {
+    pub users: Vec<User>,
+    #[schema(example = 1)]
+    pub page: i64,
+    #[schema(example = 20)]
+    pub limit: i64,
+    // Number of users matching the filters, on every page
+    pub total: i64,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct UsersResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub data: UsersData,
+}
+
+impl UsersResponse {
+    pub fn new(users: Vec<User>, page: i64, limit: i64, total: i64) -> UsersResponse {
+        UsersResponse { status: SUCCESS.to_string(), data: UsersData { users, page, limit, total } }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct RoleResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    #[schema(example = "admin")]
+    pub role: String,
+}
+
+impl RoleResponse {
+    pub fn new(role: String) -> RoleResponse {
+        RoleResponse { status: SUCCESS.to_string(), role }
+    }
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SessionInfo {
+    pub id: uuid::Uuid,
+    pub userAgent: Option<String>,
+    pub ipAddress: Option<String>,
+    // Session of the token used for the request
+    pub current: bool,
+    pub createdAt: DateTime<Utc>,
+    pub lastUsedAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SessionsData {
+    pub sessions: Vec<SessionInfo>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SessionsResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub data: SessionsData,
+}
+
+impl SessionsResponse {
+    pub fn new(sessions: Vec<SessionInfo>) -> SessionsResponse {
+        SessionsResponse { status: SUCCESS.to_string(), data: SessionsData { sessions } }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct LanguagesData {
+    pub languages: Vec<Language>,
+    #[schema(example = "en")]
+    pub default: String,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct LanguagesResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub data: LanguagesData,
+}
+
+impl LanguagesResponse {
+    pub fn new(languages: Vec<Language>, default: String) -> LanguagesResponse {
+        LanguagesResponse { status: SUCCESS.to_string(), data: LanguagesData { languages, default } }
+    }
+}
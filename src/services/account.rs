@@ -1,39 +1,93 @@
-use actix_web::{web, get, HttpRequest, HttpResponse, HttpMessage, Responder, Scope};
+use actix_web::{web, get, delete, HttpRequest, HttpResponse, HttpMessage, Responder, Scope};
 
 use crate::AppState;
-use crate::models::User;
+use crate::models::{User, Session};
+use crate::middlewares::jwt::JwtToken;
+use crate::response::{StatusResponse, UserResponse, SessionsResponse, SessionInfo};
+use crate::shared::api_error::ApiError;
 
+#[utoipa::path(
+    context_path = "/api/account",
+    tag = "account",
+    security(("bearer" = []), ("cookie" = [])),
+    responses(
+        (status = 200, description = "The access token is valid", body = StatusResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse)
+    )
+)]
 #[get("/check")]
 async fn check_handler(_req: HttpRequest, _data: web::Data<AppState>) -> impl Responder {
-    let json_response = serde_json::json!({
-        "status":  "success"
-    });
-    HttpResponse::Ok().json(json_response)
+    HttpResponse::Ok().json(StatusResponse::success())
 }
 
 
+#[utoipa::path(
+    context_path = "/api/account",
+    tag = "account",
+    security(("bearer" = []), ("cookie" = [])),
+    responses(
+        (status = 200, description = "The authenticated user", body = UserResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse)
+    )
+)]
 #[get("/users/me")]
-async fn get_me_handler(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
-    let ext = req.extensions();
-    let user_id = ext.get::<uuid::Uuid>().unwrap();
-
-    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
-        .fetch_one(&data.db)
-        .await
-        .unwrap();
-
-    let json_response = serde_json::json!({
-        "status":  "success",
-        "data": serde_json::json!({
-            "user": &user
+async fn get_me_handler(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let user_id = *req.extensions().get::<uuid::Uuid>().ok_or(ApiError::Unauthorized)?;
+
+    let user = User::get_user_from_id(user_id, &data.db).await?;
+
+    Ok(HttpResponse::Ok().json(UserResponse::new(user)))
+}
+
+#[utoipa::path(
+    context_path = "/api/account",
+    tag = "account",
+    security(("bearer" = []), ("cookie" = [])),
+    responses(
+        (status = 200, description = "Active sessions of the user", body = SessionsResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse)
+    )
+)]
+#[get("/sessions")]
+async fn get_sessions_handler(claims: JwtToken, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let sessions = Session::get_active_from_user(claims.user_id, &data.db).await?
+        .into_iter()
+        .map(|session| SessionInfo {
+            current: session.id == claims.session_id,
+            id: session.id,
+            userAgent: session.user_agent,
+            ipAddress: session.ip_address,
+            createdAt: session.created_at,
+            lastUsedAt: session.last_used_at,
         })
-    });
+        .collect();
+
+    Ok(HttpResponse::Ok().json(SessionsResponse::new(sessions)))
+}
 
-    HttpResponse::Ok().json(json_response)
+#[utoipa::path(
+    context_path = "/api/account",
+    tag = "account",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = uuid::Uuid, Path, description = "Id of the session")),
+    responses(
+        (status = 200, description = "The session and its tokens are revoked", body = StatusResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 404, description = "No active session of the user with this id", body = ErrorResponse)
+    )
+)]
+#[delete("/sessions/{id}")]
+async fn revoke_session_handler(claims: JwtToken, path: web::Path<uuid::Uuid>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    match Session::revoke(claims.user_id, path.into_inner(), &data.db).await? {
+        true => Ok(HttpResponse::Ok().json(StatusResponse::success())),
+        false => Err(ApiError::SessionNotFound)
+    }
 }
 
 pub fn init() -> Scope {
     web::scope("/account")
         .service(get_me_handler)
         .service(check_handler)
+        .service(get_sessions_handler)
+        .service(revoke_session_handler)
 }
@@ -0,0 +1,259 @@
+use actix_web::{web, get, post, delete, HttpResponse, Responder, Scope};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::AppState;
+use crate::api_schemas::{UsersQuerySchema, UpdateRoleRequestSchema};
+use crate::middlewares::jwt::Admin;
+use crate::models::{User, Token, Lockout, user::ROLES};
+use crate::response::{StatusResponse, UserResponse, UsersResponse, RoleResponse};
+use crate::shared::api_error::ApiError;
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+
+fn user_response(user: User) -> HttpResponse {
+    HttpResponse::Ok().json(UserResponse::new(user))
+}
+
+fn user_error(err: sqlx::Error) -> ApiError {
+    match err {
+        sqlx::Error::RowNotFound => ApiError::UserNotFound,
+        err => err.into()
+    }
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    responses(
+        (status = 200, description = "The access token is valid and carries the admin role", body = RoleResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse)
+    )
+)]
+#[get("/check")]
+async fn check_admin_handler(Admin(claims): Admin) -> impl Responder {
+    HttpResponse::Ok().json(RoleResponse::new(claims.role))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(UsersQuerySchema),
+    responses(
+        (status = 200, description = "A page of the users matching the filters", body = UsersResponse),
+        (status = 400, description = "The page is out of range", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse)
+    )
+)]
+#[get("/users")]
+async fn get_users_handler(query: web::Query<UsersQuerySchema>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let query = query.into_inner();
+    query.validate().map_err(ApiError::from)?;
+    let page = query.page.unwrap_or(1);
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let total = User::count_users(query.email.clone(), query.role.clone(), query.verified, &data.db).await?;
+    let users = User::get_users(query.email, query.role, query.verified, limit, (page - 1) * limit, &data.db).await?;
+
+    Ok(HttpResponse::Ok().json(UsersResponse::new(users, page, limit, total)))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = Uuid, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The user", body = UserResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "No user with this id", body = ErrorResponse)
+    )
+)]
+#[get("/users/{id}")]
+async fn get_user_handler(path: web::Path<Uuid>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let user = User::get_user_from_id(path.into_inner(), &data.db).await.map_err(user_error)?;
+    Ok(user_response(user))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = Uuid, Path, description = "Id of the user")),
+    request_body = UpdateRoleRequestSchema,
+    responses(
+        (status = 200, description = "The user with the new role, their tokens are revoked", body = UserResponse),
+        (status = 400, description = "Unknown role, or the admin is changing their own role", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "No user with this id", body = ErrorResponse)
+    )
+)]
+#[post("/users/{id}/role")]
+async fn set_role_handler(
+    Admin(claims): Admin,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdateRoleRequestSchema>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    if id == claims.user_id {
+        return Err(ApiError::SelfAction);
+    }
+    if !ROLES.contains(&body.role.as_str()) {
+        return Err(ApiError::UnknownRole { roles: ROLES.iter().map(|role| role.to_string()).collect() });
+    }
+
+    // The role is copied in the access tokens, the user signs in again to get the new one
+    let user = User::set_role(id, body.role.to_owned(), &data.db).await.map_err(user_error)?;
+    Token::invalidate_all(user.id, &data.db).await?;
+    Ok(user_response(user))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = Uuid, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The user with a verified email", body = UserResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "No user with this id", body = ErrorResponse)
+    )
+)]
+#[post("/users/{id}/verify")]
+async fn verify_handler(path: web::Path<Uuid>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let user = User::set_email_verified(path.into_inner(), &data.db).await.map_err(user_error)?;
+    Ok(user_response(user))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = Uuid, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The disabled user, their tokens are revoked", body = UserResponse),
+        (status = 400, description = "The admin is disabling their own account", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "No user with this id", body = ErrorResponse)
+    )
+)]
+#[post("/users/{id}/disable")]
+async fn disable_handler(Admin(claims): Admin, path: web::Path<Uuid>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    if id == claims.user_id {
+        return Err(ApiError::SelfAction);
+    }
+
+    let user = User::set_disabled(id, true, &data.db).await.map_err(user_error)?;
+    Token::invalidate_all(user.id, &data.db).await?;
+    Ok(user_response(user))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = Uuid, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The enabled user", body = UserResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "No user with this id", body = ErrorResponse)
+    )
+)]
+#[post("/users/{id}/enable")]
+async fn enable_handler(path: web::Path<Uuid>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let user = User::set_disabled(path.into_inner(), false, &data.db).await.map_err(user_error)?;
+    Ok(user_response(user))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = Uuid, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "Every token of the user is revoked", body = StatusResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "No user with this id", body = ErrorResponse)
+    )
+)]
+#[post("/users/{id}/revoke_sessions")]
+async fn revoke_sessions_handler(path: web::Path<Uuid>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    User::get_user_from_id(id, &data.db).await.map_err(user_error)?;
+
+    Token::invalidate_all(id, &data.db).await?;
+    Ok(HttpResponse::Ok().json(StatusResponse::success()))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = Uuid, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The lockout of the user is lifted", body = StatusResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "No user with this id", body = ErrorResponse)
+    )
+)]
+#[post("/users/{id}/unlock")]
+async fn unlock_user_handler(path: web::Path<Uuid>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    User::get_user_from_id(id, &data.db).await.map_err(user_error)?;
+
+    Lockout::unlock(id, &data.db).await?;
+    Ok(HttpResponse::Ok().json(StatusResponse::success()))
+}
+
+#[utoipa::path(
+    context_path = "/api/admin",
+    tag = "admin",
+    security(("bearer" = []), ("cookie" = [])),
+    params(("id" = Uuid, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The user and their data are deleted", body = StatusResponse),
+        (status = 400, description = "The admin is deleting their own account", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "No user with this id", body = ErrorResponse)
+    )
+)]
+#[delete("/users/{id}")]
+async fn delete_user_handler(Admin(claims): Admin, path: web::Path<Uuid>, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    if id == claims.user_id {
+        return Err(ApiError::SelfAction);
+    }
+    User::get_user_from_id(id, &data.db).await.map_err(user_error)?;
+
+    User::delete(id, &data.db).await?;
+    Ok(HttpResponse::Ok().json(StatusResponse::success()))
+}
+
+pub fn init() -> Scope {
+    web::scope("/admin")
+        .service(check_admin_handler)
+        .service(get_users_handler)
+        .service(get_user_handler)
+        .service(set_role_handler)
+        .service(verify_handler)
+        .service(disable_handler)
+        .service(enable_handler)
+        .service(revoke_sessions_handler)
+        .service(unlock_user_handler)
+        .service(delete_user_handler)
+}
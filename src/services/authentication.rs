@@ -1,188 +1,483 @@
-use actix_web::{cookie::{time::Duration as ActixWebDuration, Cookie}, web, post, HttpResponse, Responder, Scope};
+use actix_web::{cookie::Cookie, http::header, web, get, post, HttpRequest, HttpResponse, Responder, Scope};
 use chrono::prelude::*;
-use chrono::{Duration, Utc};
+use chrono::Utc;
 
-use crate::{ models::{User, Code, Token},
-             api_schemas::{RegisterRequestSchema, LoginRequestSchema, ConfirmCodeRequestSchema},
-             middlewares::jwt::JwtToken,
+use crate::{ models::{User, Code, Token, Session, Lockout, Language, MagicLink, SecurityEvent, security_event::ACCOUNT_LOCKED},
+             api_schemas::{RegisterRequestSchema, LoginRequestSchema, LoginMode, ConfirmCodeRequestSchema, RefreshRequestSchema, MagicLinkQuerySchema, MagicLinkRequestSchema, UnlockQuerySchema, ValidatedJson},
+             middlewares::jwt::{JwtToken, UnlockToken, TokenPair, refresh_tokens},
+             modules::{mail_templates::MailKind, i18n::negotiate},
+             response::{StatusResponse, TokensResponse},
+             shared::{api_error::ApiError, tools::{get_user_agent, get_ip_address, get_bearer_token, get_accept_language}},
              AppState};
 
+// Throttles the mails sent to an address and the requests of a client
+async fn rate_limit(req: &HttpRequest, email: &str, data: &AppState) -> Result<(), ApiError> {
+    let checks = [
+        (format!("ip:{}", get_ip_address(req, &data.config.trusted_proxies).unwrap_or_default()), data.rate_limiter.ip_rule),
+        (format!("email:{}", email.to_lowercase()), data.rate_limiter.email_rule),
+    ];
+
+    for (key, rule) in checks.iter() {
+        data.rate_limiter.check(key, rule).await?
+            .map_err(|retry_after| ApiError::TooManyRequests { retry_after })?;
+    }
+    Ok(())
+}
+
+async fn check_lockout(user_id: uuid::Uuid, data: &AppState) -> Result<(), ApiError> {
+    match Lockout::get_from_user(user_id, &data.db).await? {
+        Some(Lockout { locked_until: Some(locked_until), .. }) if locked_until > Utc::now() => Err(ApiError::AccountLocked { locked_until }),
+        _ => Ok(())
+    }
+}
+
+// Issues a new code and queues the mail carrying it, both or neither are saved
+async fn send_code<F>(user: &User, data: &AppState, mail: F) -> Result<(), sqlx::Error>
+where F: FnOnce(String) -> (MailKind, Vec<(&'static str, String)>) {
+    let mut transaction = data.db.begin().await?;
+    let code = Code::create_code(user.id, data.config.code_length, data.config.code_alphabet, data.config.code_secret.expose().as_bytes(), &mut *transaction).await?;
+    let (kind, mut vars) = mail(code);
+    vars.push(("ttl", data.config.code_ttl_minutes.to_string()));
+    data.mailer.queue_mail(user.email.to_owned(), kind, &user.language_id, &vars, &mut *transaction).await?;
+    transaction.commit().await
+}
+
+// Stores a new login link and queues the mail carrying it, both or neither are saved
+async fn send_magic_link(user: &User, redirect_path: Option<String>, data: &AppState) -> Result<(), sqlx::Error> {
+    let mut transaction = data.db.begin().await?;
+    let token = MagicLink::create(user.id, redirect_path, data.config.code_ttl_minutes, &mut *transaction).await?;
+    let link = format!("{}/auth/magic?token={}", data.config.backend_url, token);
+    data.mailer.queue_mail(user.email.to_owned(), MailKind::MagicLink, &user.language_id,
+        &[("link", link), ("ttl", data.config.code_ttl_minutes.to_string())], &mut *transaction).await?;
+    transaction.commit().await
+}
+
+fn front_url(data: &AppState) -> String {
+    data.config.front_url.trim_end_matches('/').to_string()
+}
+
+fn invalid_link(data: &AppState) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, format!("{}/?error=invalid_link", front_url(data))))
+        .finish()
+}
+
+// Locks the account for a cooldown doubling with every lock, and mails the user a link to unlock it
+async fn lock_account(req: &HttpRequest, user: &User, lockout: &Lockout, data: &AppState) -> ApiError {
+    let cooldown = lockout.cooldown_minutes(data.config.lockout_base_minutes, data.config.lockout_max_minutes);
+    let locked_until = Utc::now() + chrono::Duration::minutes(cooldown);
+
+    let locked: Result<(), sqlx::Error> = async {
+        let mut transaction = data.db.begin().await?;
+        let lockout = Lockout::lock(user.id, locked_until, &mut *transaction).await?;
+        Code::consume(user.id, &mut *transaction).await?;
+        SecurityEvent::record(user.id, None, ACCOUNT_LOCKED, get_user_agent(req), get_ip_address(req, &data.config.trusted_proxies), &mut *transaction).await?;
+
+        let token = UnlockToken::new(user.id, lockout.lock_count, locked_until.timestamp() as usize);
+        let link = format!("{}/auth/unlock?token={}", data.config.backend_url, token.encode(data.config.jwt_secret.expose().as_bytes()));
+
+        data.mailer.queue_mail(user.email.to_owned(), MailKind::AccountLocked, &user.language_id,
+            &[("cooldown", cooldown.to_string()), ("link", link)], &mut *transaction).await?;
+        transaction.commit().await
+    }.await;
+
+    match locked {
+        Ok(_) => ApiError::AccountLocked { locked_until },
+        Err(err) => err.into()
+    }
+}
+
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    request_body = RegisterRequestSchema,
+    responses(
+        (status = 200, description = "A code is sent to the address, the account is created if needed", body = StatusResponse),
+        (status = 400, description = "Invalid body", body = ErrorResponse),
+        (status = 403, description = "The account is disabled", body = ErrorResponse),
+        (status = 423, description = "The account is temporarily locked, see Retry-After", body = ErrorResponse),
+        (status = 429, description = "Too many requests for this address or client, see Retry-After", body = ErrorResponse)
+    )
+)]
 #[post("/register")]
 async fn register_handler(
-    body: web::Json<RegisterRequestSchema>,
+    req: HttpRequest,
+    body: ValidatedJson<RegisterRequestSchema>,
     data: web::Data<AppState>,
-) -> impl Responder {
-    let app_name = data.config.app_name.clone();
-    let exists: bool = User::is_user_exist(body.email.to_owned(), &data.db).await;
+) -> Result<HttpResponse, ApiError> {
+    rate_limit(&req, &body.email, &data).await?;
+
+    let languages: Vec<String> = Language::get_languages(&data.db).await?
+        .into_iter()
+        .map(|language| language.code)
+        .collect();
+    let language = match &body.language {
+        Some(language) => languages.iter()
+            .find(|code| code.eq_ignore_ascii_case(language))
+            .cloned()
+            .ok_or_else(|| ApiError::UnsupportedLanguage { languages: languages.clone() })?,
+        None => get_accept_language(&req)
+            .and_then(|header| negotiate(&header, &languages))
+            .unwrap_or_else(|| data.config.default_language.clone()),
+    };
+
+    let exists: bool = User::is_user_exist(body.email.to_owned(), &data.db).await?;
 
-    let insert_user_result = if !exists {
-        User::create_user(body.email.to_owned(), body.language.to_owned(), &data.db).await
+    let user = if !exists {
+        User::create_user(body.email.to_owned(), language, &data.db).await?
     } else {
-        if let Some(user) = User::get_user_from_email(body.email.to_owned(), &data.db).await {
-            Ok(user)
-        }
-        else {
-            Err(sqlx::Error::RowNotFound)
-        }
+        User::get_user_from_email(body.email.to_owned(), &data.db).await?
+            .ok_or(sqlx::Error::RowNotFound)?
     };
 
-    if let Ok(user) = insert_user_result {
-        let create_code_result = Code::create_code(user.id.to_owned(), &data.db).await;
-
-        if let Ok(code) = create_code_result {
-            if user.language_id == "fr" {
-                data.mailer.send_message(body.email.to_owned(), 
-                    format!("Confirmez votre enregistrement sur {}", app_name), 
-                    format!("Code de validation (pour 5 minutes) : {}", code.code));
-            }
-            else {
-                data.mailer.send_message(body.email.to_owned(), 
-                    format!("Confirm your registration on {}", app_name), 
-                    format!("Validation code (5 minutes): {}", code.code));
-            }
-            return HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
-        }
+    if user.disabled {
+        return Err(ApiError::AccountDisabled);
     }
-    HttpResponse::InternalServerError()
-        .json(serde_json::json!({"status": "error","message": "Internal error occured during registration request"}))
+    check_lockout(user.id, &data).await?;
+    send_code(&user, &data, |code| (MailKind::RegisterCode, vec![("code", code)])).await?;
+
+    Ok(HttpResponse::Ok().json(StatusResponse::success()))
 }
 
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    request_body = RegisterRequestSchema,
+    responses(
+        (status = 200, description = "A new code is sent when the account exists", body = StatusResponse),
+        (status = 400, description = "Invalid body", body = ErrorResponse),
+        (status = 423, description = "The account is temporarily locked, see Retry-After", body = ErrorResponse),
+        (status = 429, description = "Too many requests for this address or client, see Retry-After", body = ErrorResponse)
+    )
+)]
 #[post("/resend_code")]
 async fn resend_code_handler(
-    body: web::Json<RegisterRequestSchema>,
+    req: HttpRequest,
+    body: ValidatedJson<RegisterRequestSchema>,
     data: web::Data<AppState>,
-) -> impl Responder {
-    let app_name = data.config.app_name.clone();
-    let query_user_result = User::get_user_from_email(body.email.to_owned(), &data.db).await;
-
-    if let Some(user) = query_user_result {
-        let create_code_result = Code::create_code(user.id.to_owned(), &data.db).await;
-
-        if let Ok(code) = create_code_result {
-            if user.language_id == "fr" {
-                data.mailer.send_message(body.email.to_owned(), 
-                format!("Confirmez votre authentification sur {}", app_name), 
-                    format!("Nouveau code de validation (pour 5 minutes) : {}", code.code));
-            }
-            else {
-                data.mailer.send_message(body.email.to_owned(), 
-                    format!("Confirm your authentication on {}", app_name), 
-                    format!("New validation code (5 minutes): {}", code.code));
-            }
-        }
+) -> Result<HttpResponse, ApiError> {
+    rate_limit(&req, &body.email, &data).await?;
+    let query_user_result = User::get_user_from_email(body.email.to_owned(), &data.db).await?;
+
+    if let Some(user) = query_user_result.filter(|user| !user.disabled) {
+        check_lockout(user.id, &data).await?;
+        send_code(&user, &data, |code| (MailKind::ResendCode, vec![("code", code)])).await?;
     }
-    return HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
+    Ok(HttpResponse::Ok().json(StatusResponse::success()))
+}
+
+// Marks the user verified and opens a new session, returns the access and refresh cookies
+async fn open_session(req: &HttpRequest, user: &User, data: &AppState) -> Result<(Cookie<'static>, Cookie<'static>), ApiError> {
+    if !user.verified {
+        User::set_email_verified(user.id, &data.db).await?;
+    }
+    Code::consume(user.id, &data.db).await?;
+    Lockout::clear(user.id, &data.db).await?;
+
+    let session = Session::create(user.id, get_user_agent(req), get_ip_address(req, &data.config.trusted_proxies), &data.db).await?;
+
+    let access_token = JwtToken::generate_access_token(user.id, session.id, user.role.clone(), data.config.jwt_expires_in)?;
+    let access_cookie = access_token.generate_cookie(&data.jwt_keys, "access_cookie".to_string());
+    let refresh_token = JwtToken::generate_refresh_token(user.id, session.id, user.role.clone(), data.config.jwt_refresh_expires_in)?;
+    let refresh_cookie = refresh_token.generate_cookie(&data.jwt_keys, "refresh_cookie".to_string());
+
+    Token::declare_new(access_token.user_id, access_token.id, session.id, None, DateTime::<Utc>::from_timestamp(access_token.exp as i64, 0).unwrap(), &data.db).await?;
+    Token::declare_new(refresh_token.user_id, refresh_token.id, session.id, None, DateTime::<Utc>::from_timestamp(refresh_token.exp as i64, 0).unwrap(), &data.db).await?;
+
+    Ok((access_cookie, refresh_cookie))
 }
 
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    request_body = ConfirmCodeRequestSchema,
+    responses(
+        (status = 200, description = "Session opened, the tokens are set in cookies or returned with tokensInBody", body = TokensResponse),
+        (status = 400, description = "Invalid body, email or code, newCode is set when a new code has been sent", body = ErrorResponse),
+        (status = 403, description = "The account is disabled", body = ErrorResponse),
+        (status = 423, description = "The account is temporarily locked, see Retry-After", body = ErrorResponse)
+    )
+)]
 #[post("/confirm_code")]
 async fn confirm_code_handler(
-    body: web::Json<ConfirmCodeRequestSchema>,
+    req: HttpRequest,
+    body: ValidatedJson<ConfirmCodeRequestSchema>,
     data: web::Data<AppState>,
-) -> impl Responder {
-    let app_name = data.config.app_name.clone();
-    let mut code_is_valid = false;
-    let query_user_result = User::get_user_from_email(body.email.to_owned(), &data.db).await;
-
-    if let Some(user) = query_user_result {
-        let query_code_result = Code::get_code_from_id(user.id.clone(), &data.db).await;
-        if let Some(code) = query_code_result {
-            code_is_valid = (code.code == body.code) && (Utc::now() - Duration::minutes(5) <= code.emitted_at);
-        }
+) -> Result<HttpResponse, ApiError> {
+    let user = match User::get_user_from_email(body.email.to_owned(), &data.db).await? {
+        Some(user) => user,
+        None => return Err(ApiError::InvalidCode { new_code: false })
+    };
 
-        if code_is_valid {
-            if !user.verified {
-                if User::set_email_verified(user.id.to_owned(), &data.db).await.is_err() {
-                    return HttpResponse::InternalServerError()
-                        .json(serde_json::json!({"status": "fail", "message": "Error during account validation database request"}));    
-                }
-            }
-
-            let access_token = JwtToken::generate_access_token(user.id.clone());
-            let access_cookie = access_token.generate_cookie(data.config.jwt_secret.as_ref(), "access_cookie".to_string());
-            let refresh_token = JwtToken::generate_refresh_token(user.id.clone());
-            let refresh_cookie = refresh_token.generate_cookie(data.config.jwt_secret.as_ref(), "refresh_cookie".to_string());
-        
-            Token::declare_new(access_token.user_id.clone(), access_token.id, DateTime::<Utc>::from_timestamp(access_token.exp as i64, 0).unwrap(), &data.db).await.unwrap();
-            Token::declare_new(refresh_token.user_id.clone(), refresh_token.id, DateTime::<Utc>::from_timestamp(refresh_token.exp as i64, 0).unwrap(),  &data.db).await.unwrap();
-
-            return HttpResponse::Ok()
-                .cookie(access_cookie)
-                .cookie(refresh_cookie)
-                .json(serde_json::json!({"status": "success"}))
-        }
-        else {
-            let tries = Code::add_try(user.id.clone(), &data.db).await;
-            if tries >= data.config.max_tries {
-                let create_code_result = Code::create_code(user.id.to_owned(), &data.db).await;
-
-                if let Ok(code) = create_code_result {
-                    if user.language_id == "fr" {
-                        data.mailer.send_message(body.email.to_owned(), 
-                            format!("Déjà trois confirmations échouées. Confirmez votre authentification sur {}", app_name), 
-                            format!("Code de validation (pour 5 minutes) : {}", code.code));
-                    }
-                    else {
-                        data.mailer.send_message(body.email.to_owned(), 
-                            format!("Already three confirmations failed. Confirm your authentication on {}", app_name), 
-                            format!("Validation code (5 minutes): {}", code.code));
-                    }
-                }
-                return HttpResponse::BadRequest()
-                    .json(serde_json::json!({"status": "fail", "message": "Invalid email or code", "newCode": "true"}))
-        
-            }
+    if user.disabled {
+        return Err(ApiError::AccountDisabled);
+    }
+    check_lockout(user.id, &data).await?;
+
+    let code_is_valid = Code::get_code_from_id(user.id, &data.db).await?
+        .is_some_and(|code| code.matches(&body.code, data.config.code_secret.expose().as_bytes()) && code.is_alive(data.config.code_ttl_minutes));
+
+    if code_is_valid {
+        let (access_cookie, refresh_cookie) = open_session(&req, &user, &data).await?;
+
+        if body.tokens_in_body {
+            return Ok(HttpResponse::Ok().json(tokens_response(&TokenPair {
+                access: access_cookie.value().to_string(),
+                refresh: refresh_cookie.value().to_string()
+            })))
         }
+
+        return Ok(HttpResponse::Ok()
+            .cookie(access_cookie)
+            .cookie(refresh_cookie)
+            .json(StatusResponse::success()))
+    }
+
+    let tries = Code::add_try(user.id, &data.db).await?.ok_or(ApiError::InvalidCode { new_code: false })?;
+    if tries < data.config.max_tries {
+        return Err(ApiError::InvalidCode { new_code: false });
     }
-    HttpResponse::BadRequest()
-        .json(serde_json::json!({"status": "fail", "message": "Invalid email or code"}))
+
+    let lockout = Lockout::record_exhausted_code(user.id, data.config.lockout_window_minutes, &data.db).await?;
+    if lockout.exhausted_codes >= data.config.lockout_threshold {
+        return Err(lock_account(&req, &user, &lockout, &data).await);
+    }
+
+    send_code(&user, &data, |code|
+        (MailKind::RetryCode, vec![("code", code), ("max_tries", data.config.max_tries.to_string())])).await?;
+    Err(ApiError::InvalidCode { new_code: true })
 }
 
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    request_body = LoginRequestSchema,
+    responses(
+        (status = 200, description = "A code or a magic link is sent when the account exists", body = StatusResponse),
+        (status = 400, description = "Invalid body", body = ErrorResponse),
+        (status = 423, description = "The account is temporarily locked, see Retry-After", body = ErrorResponse),
+        (status = 429, description = "Too many requests for this address or client, see Retry-After", body = ErrorResponse)
+    )
+)]
 #[post("/login")]
 async fn login_handler(
-    body: web::Json<LoginRequestSchema>,
+    req: HttpRequest,
+    body: ValidatedJson<LoginRequestSchema>,
     data: web::Data<AppState>,
-) -> impl Responder {
-    let app_name = data.config.app_name.clone();
-    let query_user_result = User::get_user_from_email(body.email.to_owned(), &data.db).await;
-
-    if let Some(user) = query_user_result {
-        let create_code_result = Code::create_code(user.id.to_owned(), &data.db).await;
-
-        if let Ok(code) = create_code_result {
-            if user.language_id == "fr" {
-                data.mailer.send_message(body.email.to_owned(), 
-                    format!("Confirmez votre authentification sur {}", app_name), 
-                    format!("Code de validation (pour 5 minutes) : {}", code.code));
-            }
-            else {
-                data.mailer.send_message(body.email.to_owned(), 
-                    format!("Confirm your authentication on {}", app_name), 
-                    format!("Validation code (5 minutes): {}", code.code));
-            }
+) -> Result<HttpResponse, ApiError> {
+    rate_limit(&req, &body.email, &data).await?;
+
+    // Only paths are accepted so that the link cannot redirect outside of the front application
+    if let Some(path) = &body.redirect_path {
+        if !path.starts_with('/') || path.starts_with("//") {
+            return Err(ApiError::InvalidRedirectPath);
         }
     }
-    return HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
-}
 
+    let query_user_result = User::get_user_from_email(body.email.to_owned(), &data.db).await?;
 
-#[post("/logout")]
-async fn logout_handler() -> impl Responder {
-    let access_cookie = Cookie::build("access_cookie", "")
-        .path("/")
-        .max_age(ActixWebDuration::new(-1, 0))
-        .http_only(true)
-        .finish();
-    let refresh_cookie = Cookie::build("refresh_cookie", "")
-        .path("/")
-        .max_age(ActixWebDuration::new(-1, 0))
-        .http_only(true)
-        .finish();
+    if let Some(user) = query_user_result.filter(|user| !user.disabled) {
+        check_lockout(user.id, &data).await?;
+        if body.mode == LoginMode::Link {
+            send_magic_link(&user, body.redirect_path.to_owned(), &data).await?;
+        }
+        else {
+            send_code(&user, &data, |code| (MailKind::LoginCode, vec![("code", code)])).await?;
+        }
+    }
+    Ok(HttpResponse::Ok().json(StatusResponse::success()))
+}
 
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    params(MagicLinkQuerySchema),
+    responses(
+        (status = 200, description = "Page posting the token to sign in, or a redirect to the front application with ?error=invalid_link", content_type = "text/html")
+    )
+)]
+#[get("/magic")]
+async fn magic_link_handler(
+    req: HttpRequest,
+    query: web::Query<MagicLinkQuerySchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if !MagicLink::is_well_formed(&query.token) {
+        return invalid_link(&data);
+    }
 
+    // Mail scanners open the links they find, so the link only signs in when the page is submitted
+    let language = data.i18n.language(&req);
+    let text = |key: &str| data.i18n.message(&language, key).unwrap_or_default();
     HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .insert_header((header::REFERRER_POLICY, "no-referrer"))
+        .body(format!(r#"<!DOCTYPE html>
+<html lang="{}">
+  <head>
+    <meta charset="utf-8">
+    <meta name="robots" content="noindex">
+    <title>{}</title>
+  </head>
+  <body>
+    <form method="post" action="magic">
+      <input type="hidden" name="token" value="{}">
+      <button type="submit">{}</button>
+    </form>
+  </body>
+</html>"#, language, text("magic_link_title"), query.token, text("magic_link_button")))
+}
+
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    request_body(content = MagicLinkRequestSchema, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Redirects to the front application with the session cookies, or with ?error=invalid_link")
+    )
+)]
+#[post("/magic")]
+async fn magic_link_confirm_handler(
+    req: HttpRequest,
+    form: web::Form<MagicLinkRequestSchema>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let link = match MagicLink::consume(&form.token, &data.db).await? {
+        Some(link) => link,
+        None => return Ok(invalid_link(&data))
+    };
+    let user = match User::get_user_from_id(link.user_id, &data.db).await {
+        Ok(user) if !user.disabled => user,
+        _ => return Ok(invalid_link(&data))
+    };
+    if Lockout::get_from_user(user.id, &data.db).await?.is_some_and(|lockout| lockout.is_locked()) {
+        return Ok(invalid_link(&data));
+    }
+
+    let (access_cookie, refresh_cookie) = open_session(&req, &user, &data).await?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, format!("{}{}", front_url(&data), link.redirect_path.unwrap_or("/".to_string()))))
         .cookie(access_cookie)
         .cookie(refresh_cookie)
-        .json(serde_json::json!({"status": "success"}))
+        .finish())
+}
+
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    params(UnlockQuerySchema),
+    responses(
+        (status = 303, description = "Redirects to the front application with ?unlocked=true, or with ?error=invalid_link")
+    )
+)]
+#[get("/unlock")]
+async fn unlock_handler(
+    query: web::Query<UnlockQuerySchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let token = match UnlockToken::decode(&query.token, data.config.jwt_secret.expose().as_bytes()) {
+        Ok(token) => token,
+        Err(_) => return invalid_link(&data)
+    };
+
+    // A link only unlocks the lock it was sent for, and only once
+    match Lockout::get_from_user(token.user_id, &data.db).await {
+        Ok(Some(lockout)) if lockout.is_locked() && lockout.lock_count == token.lock_count => (),
+        _ => return invalid_link(&data)
+    }
+
+    match Lockout::unlock(token.user_id, &data.db).await {
+        Ok(_) => HttpResponse::SeeOther()
+            .insert_header((header::LOCATION, format!("{}/?unlocked=true", front_url(&data))))
+            .finish(),
+        Err(_) => invalid_link(&data)
+    }
+}
+
+fn tokens_response(pair: &TokenPair) -> TokensResponse {
+    TokensResponse::bearer(pair.access.clone(), pair.refresh.clone())
+}
+
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    request_body = RefreshRequestSchema,
+    responses(
+        (status = 200, description = "New access and refresh tokens", body = TokensResponse),
+        (status = 401, description = "Invalid or revoked refresh token", body = ErrorResponse),
+        (status = 409, description = "The refresh token was already used by a concurrent request", body = ErrorResponse)
+    )
+)]
+#[post("/refresh")]
+async fn refresh_handler(
+    req: HttpRequest,
+    body: web::Json<RefreshRequestSchema>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    match refresh_tokens(&body.refresh_token, &req, &data).await? {
+        (_, Some(pair)) => Ok(HttpResponse::Ok().json(tokens_response(&pair))),
+        (_, None) => Err(ApiError::RefreshTokenUsed)
+    }
+}
+
+// Every token the client presented, from the Authorization header and the cookies
+fn request_tokens(req: &HttpRequest, data: &AppState) -> Vec<JwtToken> {
+    let cookies = ["access_cookie", "refresh_cookie"].into_iter()
+        .filter_map(|name| req.cookie(name).map(|cookie| cookie.value().to_string()));
+
+    get_bearer_token(req).into_iter()
+        .chain(cookies)
+        .filter_map(|value| JwtToken::decode(&value, &data.jwt_keys).ok())
+        .collect()
+}
+
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    responses(
+        (status = 200, description = "The sessions of the presented tokens are revoked and the cookies cleared", body = StatusResponse)
+    )
+)]
+#[post("/logout")]
+async fn logout_handler(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    // A bearer client only presents its access token, the refresh token of the session goes with it
+    for token in request_tokens(&req, &data) {
+        Session::revoke(token.user_id, token.session_id, &data.db).await?;
+    }
+
+    Ok(HttpResponse::Ok()
+        .cookie(JwtToken::expired_cookie("access_cookie".to_string()))
+        .cookie(JwtToken::expired_cookie("refresh_cookie".to_string()))
+        .json(StatusResponse::success()))
+}
+
+#[utoipa::path(
+    context_path = "/auth",
+    tag = "authentication",
+    responses(
+        (status = 200, description = "Every token of the user is revoked and the cookies cleared", body = StatusResponse),
+        (status = 401, description = "No valid token presented", body = ErrorResponse)
+    )
+)]
+#[post("/logout_all")]
+async fn logout_all_handler(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let mut user_id = None;
+
+    for token in request_tokens(&req, &data) {
+        if let Ok(true) = Token::is_valid(token.user_id, token.id, &data.db).await {
+            user_id = Some(token.user_id);
+            break;
+        }
+    }
+
+    let user_id = user_id.ok_or(ApiError::Unauthorized)?;
+    Token::invalidate_all(user_id, &data.db).await?;
+
+    Ok(HttpResponse::Ok()
+        .cookie(JwtToken::expired_cookie("access_cookie".to_string()))
+        .cookie(JwtToken::expired_cookie("refresh_cookie".to_string()))
+        .json(StatusResponse::success()))
 }
 
 pub fn init() -> Scope {
@@ -191,5 +486,10 @@ pub fn init() -> Scope {
         .service(confirm_code_handler)
         .service(login_handler)
         .service(logout_handler)
+        .service(logout_all_handler)
+        .service(refresh_handler)
+        .service(magic_link_handler)
+        .service(magic_link_confirm_handler)
+        .service(unlock_handler)
         .service(resend_code_handler)
 }
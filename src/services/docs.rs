@@ -0,0 +1,92 @@
+use actix_web::{web, get, HttpResponse, Responder};
+use utoipa::OpenApi;
+
+use crate::AppState;
+use crate::modules::openapi::ApiDoc;
+
+#[utoipa::path(
+    tag = "docs",
+    responses((status = 200, description = "This OpenAPI document", content_type = "application/json"))
+)]
+#[get("/api/openapi.json")]
+async fn openapi_handler() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+#[utoipa::path(
+    tag = "docs",
+    responses(
+        (status = 200, description = "Page rendering the document with the API_DOCS_UI", content_type = "text/html"),
+        (status = 404, description = "API_DOCS_UI is none")
+    )
+)]
+#[get("/api/docs")]
+async fn docs_handler(data: web::Data<AppState>) -> impl Responder {
+    let ui = data.config.api_docs_ui;
+    match ui.page("/api/openapi.json") {
+        Some(page) => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .insert_header(("Content-Security-Policy", ui.content_security_policy("/api/openapi.json")))
+            .body(page),
+        None => HttpResponse::NotFound().finish()
+    }
+}
+
+// Registered before the `/api` scope so that the documentation does not require authentication
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(openapi_handler)
+        .service(docs_handler);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::BTreeSet, rc::Rc};
+    use actix_web::{dev::{ResourceMap, Service}, test, App};
+    use utoipa::OpenApi;
+
+    use super::ApiDoc;
+    use crate::services;
+
+    // The routes are registered by the handler macros, which name each resource after its handler
+    // like utoipa names the operations, and utoipa reads the method from the same macro. The
+    // resource map has no iterator, the names are read from its debug output
+    fn resource_names(map: &ResourceMap) -> BTreeSet<String> {
+        let map = format!("{:?}", map);
+        map.split("name: Some(\"").skip(1)
+            .map(|rest| rest[..rest.find('"').unwrap()].to_string())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn spec_matches_routes() {
+        let captured: Rc<RefCell<Option<ResourceMap>>> = Rc::default();
+        let capture = Rc::clone(&captured);
+        let app = test::init_service(App::new()
+            .configure(services::configure)
+            .wrap_fn(move |req, srv| {
+                capture.replace(Some(req.resource_map().clone()));
+                srv.call(req)
+            })).await;
+        test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        let map = captured.take().unwrap();
+        let request = test::TestRequest::default().to_http_request();
+
+        let id = uuid::Uuid::nil().to_string();
+        let mut documented = BTreeSet::new();
+        for (path, item) in ApiDoc::openapi().paths.paths {
+            for (_, operation) in item.operations {
+                let name = operation.operation_id.unwrap();
+                let url = map.url_for(&request, &name, path.matches("{id}").map(|_| &id))
+                    .unwrap_or_else(|_| panic!("{} {} is documented but not routed", name, path));
+                assert_eq!(url.path(), path.replace("{id}", &id), "{} is documented at another path", name);
+                assert!(documented.insert(name.clone()), "{} is documented twice", name);
+            }
+        }
+
+        let routed = resource_names(&map);
+        assert!(routed.is_superset(&documented), "the resource names are missing from the map");
+        for name in routed {
+            assert!(documented.contains(&name), "{} is routed but not documented", name);
+        }
+    }
+}
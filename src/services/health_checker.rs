@@ -1,10 +1,17 @@
 use actix_web::{web, get, HttpResponse, Responder, Scope};
 
+use crate::response::MessageResponse;
+
+#[utoipa::path(
+    context_path = "/service",
+    tag = "service",
+    responses((status = 200, description = "The service is up", body = MessageResponse))
+)]
 #[get("/healthchecker")]
 async fn health_checker_handler() -> impl Responder {
     const MESSAGE: &str = "Healthcheck OK";
 
-    HttpResponse::Ok().json(serde_json::json!({"status": "success", "message": MESSAGE}))
+    HttpResponse::Ok().json(MessageResponse::success(MESSAGE))
 }
 
 pub fn init() -> Scope {
@@ -0,0 +1,26 @@
+use actix_web::{web, get, HttpResponse, Scope};
+
+use crate::AppState;
+use crate::models::Language;
+use crate::response::LanguagesResponse;
+use crate::shared::api_error::ApiError;
+
+#[utoipa::path(
+    context_path = "/languages",
+    tag = "languages",
+    responses(
+        (status = 200, description = "Supported languages and the default one", body = LanguagesResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+#[get("")]
+async fn get_languages_handler(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let languages = Language::get_languages(&data.db).await?;
+
+    Ok(HttpResponse::Ok().json(LanguagesResponse::new(languages, data.config.default_language.clone())))
+}
+
+pub fn init() -> Scope {
+    web::scope("/languages")
+        .service(get_languages_handler)
+}
@@ -1,3 +1,25 @@
 pub mod health_checker;
 pub mod authentication;
 pub mod account;
+pub mod admin;
+pub mod languages;
+pub mod docs;
+pub mod well_known;
+
+use actix_web::web;
+
+use crate::middlewares::jwt::{AuthRequired, RoleRequired};
+use crate::models::user::ROLE_ADMIN;
+
+// Every route of the API, the application wide middlewares are added by main
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(health_checker::init())
+        .service(authentication::init())
+        .service(languages::init())
+        .service(well_known::init())
+        .configure(docs::init)
+        .service(web::scope("/api")
+            .wrap(AuthRequired)
+            .service(account::init())
+            .service(admin::init().wrap(RoleRequired(ROLE_ADMIN))));
+}
@@ -0,0 +1,20 @@
+use actix_web::{web, get, http::header, HttpResponse, Responder, Scope};
+
+use crate::AppState;
+
+#[utoipa::path(
+    context_path = "/.well-known",
+    tag = "service",
+    responses((status = 200, description = "Public keys verifying the access tokens, empty with HS256", content_type = "application/json"))
+)]
+#[get("/jwks.json")]
+async fn jwks_handler(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "public, max-age=300"))
+        .json(data.jwt_keys.jwks())
+}
+
+pub fn init() -> Scope {
+    web::scope("/.well-known")
+        .service(jwks_handler)
+}
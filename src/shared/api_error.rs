@@ -0,0 +1,162 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+use actix_web::{http::{header, StatusCode}, HttpResponse, ResponseError};
+use chrono::{DateTime, Utc};
+use validator::ValidationErrors;
+
+use crate::modules::i18n::I18n;
+use crate::response::{ErrorResponse, FieldError};
+
+// Every failure returned to clients, the code is stable and keys the message in the locales
+#[derive(Debug)]
+pub enum ApiError {
+    InvalidJson { detail: String },
+    // Error codes of each invalid field
+    InvalidRequest { fields: BTreeMap<String, Vec<String>> },
+    Unauthorized,
+    Forbidden,
+    AccountDisabled,
+    AccountLocked { locked_until: DateTime<Utc> },
+    TooManyRequests { retry_after: Duration },
+    InvalidCode { new_code: bool },
+    InvalidRedirectPath,
+    UnsupportedLanguage { languages: Vec<String> },
+    UnknownRole { roles: Vec<String> },
+    SelfAction,
+    RefreshTokenUsed,
+    UserNotFound,
+    SessionNotFound,
+    Database(sqlx::Error),
+    // A setting the server cannot work with, like a token lifetime out of the date range
+    Internal,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidJson { .. } => "invalid_json",
+            ApiError::InvalidRequest { .. } => "invalid_request",
+            ApiError::Unauthorized => "not_logged_in",
+            ApiError::Forbidden => "forbidden",
+            ApiError::AccountDisabled => "account_disabled",
+            ApiError::AccountLocked { .. } => "account_locked",
+            ApiError::TooManyRequests { .. } => "too_many_requests",
+            ApiError::InvalidCode { .. } => "invalid_email_or_code",
+            ApiError::InvalidRedirectPath => "invalid_redirect_path",
+            ApiError::UnsupportedLanguage { .. } => "unsupported_language",
+            ApiError::UnknownRole { .. } => "unknown_role",
+            ApiError::SelfAction => "self_action",
+            ApiError::RefreshTokenUsed => "refresh_token_used",
+            ApiError::UserNotFound => "user_not_found",
+            ApiError::SessionNotFound => "session_not_found",
+            ApiError::Database(_) => "database_error",
+            ApiError::Internal => "internal_error",
+        }
+    }
+
+    // Replaces the `{name}` placeholders of a message
+    fn format_message(&self, message: String) -> String {
+        match self {
+            ApiError::UnsupportedLanguage { languages } => message.replace("{languages}", &languages.join(", ")),
+            ApiError::UnknownRole { roles } => message.replace("{roles}", &roles.join(", ")),
+            _ => message
+        }
+    }
+
+    // `translate` gives the message of a code, the field errors are translated too
+    fn response(&self, translate: &dyn Fn(&str) -> String) -> HttpResponse {
+        let message = self.format_message(translate(self.code()));
+        let status = if self.status_code().is_server_error() { "error" } else { "fail" };
+        let mut body = ErrorResponse {
+            status: status.to_string(),
+            code: self.code().to_string(),
+            message,
+            detail: None,
+            errors: None,
+            lockedUntil: None,
+            newCode: None,
+        };
+        let mut builder = HttpResponse::build(self.status_code());
+
+        match self {
+            ApiError::InvalidJson { detail } => {
+                body.detail = Some(detail.clone());
+            },
+            ApiError::InvalidRequest { fields } => {
+                body.errors = Some(fields.iter()
+                    .map(|(field, codes)| (field.clone(), codes.iter()
+                        .map(|code| FieldError { code: code.clone(), message: translate(code) })
+                        .collect()))
+                    .collect());
+            },
+            ApiError::AccountLocked { locked_until } => {
+                let retry_after = (*locked_until - Utc::now()).num_seconds().max(0);
+                builder.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+                body.lockedUntil = Some(*locked_until);
+            },
+            ApiError::TooManyRequests { retry_after } => {
+                builder.insert_header((header::RETRY_AFTER, retry_after.as_secs().to_string()));
+            },
+            ApiError::InvalidCode { new_code: true } => {
+                body.newCode = Some("true".to_string());
+            },
+            _ => ()
+        }
+        builder.json(body)
+    }
+
+    // Falls back to the code when no locale has a message for it
+    pub fn localized_response(&self, i18n: &I18n, language: &str) -> HttpResponse {
+        self.response(&|code| i18n.message(language, code).unwrap_or_else(|| code.to_string()))
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Database(err) => write!(f, "{}: {}", self.code(), err),
+            _ => write!(f, "{}", self.code())
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden | ApiError::AccountDisabled => StatusCode::FORBIDDEN,
+            ApiError::AccountLocked { .. } => StatusCode::LOCKED,
+            ApiError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::InvalidJson { .. }
+                | ApiError::InvalidRequest { .. }
+                | ApiError::InvalidCode { .. }
+                | ApiError::InvalidRedirectPath
+                | ApiError::UnsupportedLanguage { .. }
+                | ApiError::UnknownRole { .. }
+                | ApiError::SelfAction => StatusCode::BAD_REQUEST,
+            ApiError::RefreshTokenUsed => StatusCode::CONFLICT,
+            ApiError::UserNotFound | ApiError::SessionNotFound => StatusCode::NOT_FOUND,
+            ApiError::Database(_) | ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // Responses are localized by the `Localize` middleware, this one is only used without it
+    fn error_response(&self) -> HttpResponse {
+        self.response(&|code| code.to_string())
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        println!("🔥 Database error: {:?}", err);
+        ApiError::Database(err)
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        let fields = errors.field_errors().into_iter()
+            .map(|(field, errors)| (field.to_string(), errors.iter().map(|error| error.code.to_string()).collect()))
+            .collect();
+        ApiError::InvalidRequest { fields }
+    }
+}
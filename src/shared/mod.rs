@@ -1 +1,3 @@
 pub mod tools;
+pub mod api_error;
+pub mod secret;
@@ -1,5 +1,18 @@
+use std::net::IpAddr;
+use actix_web::{http::header, HttpRequest};
 use rand::Rng;
 
+// Ambiguous characters (0/O, 1/I) are left out so codes can be typed back reliably
+const ALPHANUMERIC_CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+pub fn generate_string_alphanumeric(size: u8) -> String {
+    let mut rng = rand::thread_rng();
+
+    (0..size)
+        .map(|_| ALPHANUMERIC_CHARSET[rng.gen_range(0..ALPHANUMERIC_CHARSET.len())] as char)
+        .collect()
+}
+
 pub fn generate_string_number(size: u8) -> String {
     let mut str: String = "".to_owned();
     let mut rng = rand::thread_rng();
@@ -9,3 +22,104 @@ pub fn generate_string_number(size: u8) -> String {
     }
     str
 }
+
+pub fn get_user_agent(req: &HttpRequest) -> Option<String> {
+    req.headers().get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+// Forwarded addresses are only read from trusted proxies, from the right since the client can write
+// any entry on the left of the ones appended by the proxies
+pub fn get_ip_address(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let mut address = req.peer_addr()?.ip();
+    if !trusted_proxies.contains(&address) {
+        return Some(address.to_string());
+    }
+
+    let forwarded: Vec<&str> = req.headers().get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    for entry in forwarded.into_iter().rev() {
+        match entry.trim().parse::<IpAddr>() {
+            Ok(ip) => address = ip,
+            Err(_) => break
+        }
+        if !trusted_proxies.contains(&address) {
+            break;
+        }
+    }
+    Some(address.to_string())
+}
+
+// Authentication schemes are case insensitive (RFC 7235)
+pub fn get_bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers().get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+        .map(|(_, token)| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+pub fn get_accept_language(req: &HttpRequest) -> Option<String> {
+    req.headers().get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use actix_web::test::TestRequest;
+
+    use super::{get_bearer_token, get_ip_address};
+
+    fn proxies(addresses: &[&str]) -> Vec<IpAddr> {
+        addresses.iter().map(|address| address.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn forwarded_addresses_are_ignored_from_untrusted_peers() {
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.7:4000".parse().unwrap())
+            .insert_header(("x-forwarded-for", "198.51.100.1"))
+            .to_http_request();
+
+        assert_eq!(get_ip_address(&req, &[]), Some("203.0.113.7".to_string()));
+        assert_eq!(get_ip_address(&req, &proxies(&["10.0.0.1"])), Some("203.0.113.7".to_string()));
+    }
+
+    #[test]
+    fn forwarded_addresses_are_read_from_the_right_behind_trusted_proxies() {
+        // The client wrote 1.2.3.4 itself, the first proxy appended the client address
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .insert_header(("x-forwarded-for", "1.2.3.4, 198.51.100.1, 10.0.0.2"))
+            .to_http_request();
+
+        assert_eq!(get_ip_address(&req, &proxies(&["10.0.0.1", "10.0.0.2"])), Some("198.51.100.1".to_string()));
+        assert_eq!(get_ip_address(&req, &proxies(&["10.0.0.1"])), Some("10.0.0.2".to_string()));
+    }
+
+    #[test]
+    fn malformed_forwarded_entries_stop_the_walk() {
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .insert_header(("x-forwarded-for", "198.51.100.1, unknown"))
+            .to_http_request();
+
+        assert_eq!(get_ip_address(&req, &proxies(&["10.0.0.1"])), Some("10.0.0.1".to_string()));
+    }
+
+    #[test]
+    fn bearer_scheme_is_case_insensitive() {
+        for header in ["Bearer abc", "bearer abc", "BEARER  abc "] {
+            let req = TestRequest::default().insert_header(("authorization", header)).to_http_request();
+            assert_eq!(get_bearer_token(&req), Some("abc".to_string()));
+        }
+        let req = TestRequest::default().insert_header(("authorization", "Basic abc")).to_http_request();
+        assert_eq!(get_bearer_token(&req), None);
+    }
+}